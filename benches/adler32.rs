@@ -4,42 +4,90 @@ use criterion::{
 };
 use rand::Rng;
 
-fn adler32(bytes: &[u8]) {
+fn adler32(bytes: &[u8]) -> u32 {
     let mut adler32 = adler32::RollingAdler32::new();
     adler32.update_buffer(bytes);
-    adler32.hash();
+    adler32.hash()
 }
 
-fn adler32fast_baseline(bytes: &[u8]) {
+fn adler32fast(bytes: &[u8]) -> u32 {
+    let mut adler32 = adler32fast::Adler32::new();
+    adler32.update(bytes);
+    adler32.as_u32()
+}
+
+fn adler32fast_baseline(bytes: &[u8]) -> u32 {
     let mut adler32 = adler32fast::baseline::State::new(1);
     adler32.update(bytes);
-    adler32.finalize();
+    adler32.finalize()
+}
+
+fn adler32fast_swar(bytes: &[u8]) -> u32 {
+    let mut adler32 = adler32fast::baseline::swar::State::new(1);
+    adler32.update(bytes);
+    adler32.finalize()
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn adler32fast_sse2(bytes: &[u8]) -> u32 {
+    let mut adler32 = adler32fast::specialized::sse2::State::new(1).unwrap();
+    adler32.update(bytes);
+    adler32.finalize()
 }
 
 #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-fn adler32fast_ssse3(bytes: &[u8]) {
+fn adler32fast_ssse3(bytes: &[u8]) -> u32 {
     let mut adler32 = adler32fast::specialized::ssse3::State::new(1).unwrap();
     adler32.update(bytes);
-    adler32.finalize();
+    adler32.finalize()
 }
 
 #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
-fn adler32fast_avx2(bytes: &[u8]) {
+fn adler32fast_avx2(bytes: &[u8]) -> u32 {
     let mut adler32 = adler32fast::specialized::avx2::State::new(1).unwrap();
     adler32.update(bytes);
-    adler32.finalize();
+    adler32.finalize()
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn adler32fast_avx512bw(bytes: &[u8]) -> u32 {
+    let mut adler32 = adler32fast::specialized::avx512bw::State::new(1).unwrap();
+    adler32.update(bytes);
+    adler32.finalize()
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn adler32fast_avx512vnni(bytes: &[u8]) -> u32 {
+    let mut adler32 = adler32fast::specialized::avx512vnni::State::new(1).unwrap();
+    adler32.update(bytes);
+    adler32.finalize()
 }
 
 #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 fn x86_group(group: &mut BenchmarkGroup<WallTime>, bytes: &[u8]) {
+    if adler32fast::specialized::sse2::State::new(1).is_some() {
+        group.bench_function("adler32fast-sse2", |b| {
+            b.iter(|| adler32fast_sse2(black_box(bytes)))
+        });
+    }
     if adler32fast::specialized::ssse3::State::new(1).is_some() {
         group.bench_function("adler32fast-ssse3", |b| {
-            b.iter(|| adler32fast_ssse3(black_box(&bytes)))
+            b.iter(|| adler32fast_ssse3(black_box(bytes)))
         });
     }
     if adler32fast::specialized::avx2::State::new(1).is_some() {
         group.bench_function("adler32fast-avx2", |b| {
-            b.iter(|| adler32fast_avx2(black_box(&bytes)))
+            b.iter(|| adler32fast_avx2(black_box(bytes)))
+        });
+    }
+    if adler32fast::specialized::avx512bw::State::new(1).is_some() {
+        group.bench_function("adler32fast-avx512bw", |b| {
+            b.iter(|| adler32fast_avx512bw(black_box(bytes)))
+        });
+    }
+    if adler32fast::specialized::avx512vnni::State::new(1).is_some() {
+        group.bench_function("adler32fast-avx512vnni", |b| {
+            b.iter(|| adler32fast_avx512vnni(black_box(bytes)))
         });
     }
 }
@@ -47,13 +95,37 @@ fn x86_group(group: &mut BenchmarkGroup<WallTime>, bytes: &[u8]) {
 #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
 fn x86_group(_: &mut BenchmarkGroup<WallTime>, _: &[u8]) {}
 
+#[cfg(target_arch = "aarch64")]
+fn adler32fast_neon(bytes: &[u8]) -> u32 {
+    let mut adler32 = adler32fast::specialized::neon::State::new(1).unwrap();
+    adler32.update(bytes);
+    adler32.finalize()
+}
+
+#[cfg(target_arch = "aarch64")]
+fn aarch64_group(group: &mut BenchmarkGroup<WallTime>, bytes: &[u8]) {
+    if adler32fast::specialized::neon::State::new(1).is_some() {
+        group.bench_function("adler32fast-neon", |b| {
+            b.iter(|| adler32fast_neon(black_box(bytes)))
+        });
+    }
+}
+
+#[cfg(not(target_arch = "aarch64"))]
+fn aarch64_group(_: &mut BenchmarkGroup<WallTime>, _: &[u8]) {}
+
 fn bench_all(mut group: BenchmarkGroup<WallTime>, bytes: &[u8]) {
     group.throughput(Throughput::Bytes(bytes.len() as u64));
-    group.bench_function("adler32", |b| b.iter(|| adler32(black_box(&bytes))));
+    group.bench_function("adler32", |b| b.iter(|| adler32(black_box(bytes))));
+    group.bench_function("adler32fast", |b| b.iter(|| adler32fast(black_box(bytes))));
     group.bench_function("adler32fast-baseline", |b| {
-        b.iter(|| adler32fast_baseline(black_box(&bytes)))
+        b.iter(|| adler32fast_baseline(black_box(bytes)))
+    });
+    group.bench_function("adler32fast-swar", |b| {
+        b.iter(|| adler32fast_swar(black_box(bytes)))
     });
     x86_group(&mut group, bytes);
+    aarch64_group(&mut group, bytes);
     group.finish();
 }
 
@@ -68,9 +140,131 @@ macro_rules! benchmark {
     };
 }
 
+benchmark!(adler32_8b, "adler32-8b", 8);
+benchmark!(adler32_16b, "adler32-16b", 16);
+benchmark!(adler32_31b, "adler32-31b", 31);
+benchmark!(adler32_63b, "adler32-63b", 63);
+benchmark!(adler32_64b, "adler32-64b", 64);
 benchmark!(adler32_1kb, "adler32-1kb", 1_000);
 benchmark!(adler32_100kb, "adler32-100kb", 100_000);
 benchmark!(adler32_10mb, "adler32-10mb", 10_000_000);
 
-criterion_group!(benches, adler32_1kb, adler32_100kb, adler32_10mb);
+fn adler32_many_4kb(c: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+    let bytes: Vec<u8> = (0..64 * 4096).map(|_| rng.gen_range(0..=255)).collect();
+    let bufs: Vec<&[u8]> = bytes.chunks(4096).collect();
+    let mut out = vec![0; bufs.len()];
+    let mut group = c.benchmark_group("adler32-many-4kb");
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+    group.bench_function("adler32fast", |b| {
+        b.iter(|| {
+            for (buf, checksum) in black_box(&bufs).iter().zip(&mut out) {
+                *checksum = adler32fast(buf);
+            }
+        })
+    });
+    group.bench_function("adler32fast-checksum_many", |b| {
+        b.iter(|| adler32fast::checksum_many_into(black_box(&bufs), &mut out))
+    });
+    group.finish();
+}
+
+fn adler32_many_256b(c: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+    let bytes: Vec<u8> = (0..64 * 256).map(|_| rng.gen_range(0..=255)).collect();
+    let bufs: Vec<&[u8]> = bytes.chunks(256).collect();
+    let mut out = vec![0; bufs.len()];
+    let mut group = c.benchmark_group("adler32-many-256b");
+    group.throughput(Throughput::Bytes(bytes.len() as u64));
+    group.bench_function("adler32fast", |b| {
+        b.iter(|| {
+            for (buf, checksum) in black_box(&bufs).iter().zip(&mut out) {
+                *checksum = adler32fast(buf);
+            }
+        })
+    });
+    group.bench_function("adler32fast-checksum_many", |b| {
+        b.iter(|| adler32fast::checksum_many_into(black_box(&bufs), &mut out))
+    });
+    group.finish();
+}
+
+fn adler32_copy(c: &mut Criterion, name: &str, size: usize) {
+    let mut rng = rand::thread_rng();
+    let src: Vec<u8> = (0..size).map(|_| rng.gen_range(0..=255)).collect();
+    let mut dst = vec![0; size];
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Bytes(size as u64));
+    group.bench_function("adler32fast-copy-update", |b| {
+        b.iter(|| {
+            dst.copy_from_slice(black_box(&src));
+            adler32fast(&dst)
+        })
+    });
+    group.bench_function("adler32fast-update_copy", |b| {
+        b.iter(|| {
+            let mut adler32 = adler32fast::Adler32::new();
+            adler32.update_copy(&mut dst, black_box(&src));
+            adler32.as_u32()
+        })
+    });
+    group.finish();
+}
+
+fn adler32_copy_100kb(c: &mut Criterion) {
+    adler32_copy(c, "adler32-copy-100kb", 100_000);
+}
+
+fn adler32_copy_10mb(c: &mut Criterion) {
+    adler32_copy(c, "adler32-copy-10mb", 10_000_000);
+}
+
+fn adler32_crc32(c: &mut Criterion, name: &str, size: usize) {
+    let mut rng = rand::thread_rng();
+    let bytes: Vec<u8> = (0..size).map(|_| rng.gen_range(0..=255)).collect();
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Bytes(size as u64));
+    group.bench_function("adler32fast-crc32fast", |b| {
+        b.iter(|| {
+            (
+                adler32fast(black_box(&bytes)),
+                crc32fast::hash(black_box(&bytes)),
+            )
+        })
+    });
+    group.bench_function("adler32fast-Adler32Crc32", |b| {
+        b.iter(|| {
+            let mut hasher = adler32fast::Adler32Crc32::new();
+            hasher.update(black_box(&bytes));
+            (hasher.adler32(), hasher.crc32())
+        })
+    });
+    group.finish();
+}
+
+fn adler32_crc32_100kb(c: &mut Criterion) {
+    adler32_crc32(c, "adler32-crc32-100kb", 100_000);
+}
+
+fn adler32_crc32_10mb(c: &mut Criterion) {
+    adler32_crc32(c, "adler32-crc32-10mb", 10_000_000);
+}
+
+criterion_group!(
+    benches,
+    adler32_8b,
+    adler32_16b,
+    adler32_31b,
+    adler32_63b,
+    adler32_64b,
+    adler32_1kb,
+    adler32_100kb,
+    adler32_10mb,
+    adler32_many_4kb,
+    adler32_many_256b,
+    adler32_copy_100kb,
+    adler32_copy_10mb,
+    adler32_crc32_100kb,
+    adler32_crc32_10mb
+);
 criterion_main!(benches);
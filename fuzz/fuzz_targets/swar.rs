@@ -0,0 +1,8 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut adler32 = adler32fast::baseline::swar::State::new(1);
+    adler32.update(data);
+    adler32.finalize();
+});
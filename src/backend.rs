@@ -0,0 +1,217 @@
+#[cfg(not(feature = "std"))]
+use core::fmt;
+#[cfg(feature = "std")]
+use std::fmt;
+
+/// Identifies an Adler-32 implementation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Backend {
+    /// The portable scalar implementation, available on every target.
+    Baseline,
+    /// The `x86`/`x86_64` implementation using SSSE3 instructions.
+    Ssse3,
+    /// The `x86`/`x86_64` implementation using AVX2 instructions.
+    Avx2,
+    /// The `x86`/`x86_64` implementation using AVX-512BW instructions.
+    Avx512Bw,
+    /// The `x86`/`x86_64` implementation using AVX-512BW and AVX-512 VNNI instructions.
+    Avx512Vnni,
+    /// The `x86`/`x86_64` implementation using SSE2 instructions.
+    Sse2,
+    /// The `aarch64` implementation using NEON instructions.
+    Neon,
+    /// The `wasm32` implementation using `simd128` instructions, selected at compile time.
+    Simd128,
+    /// The target-independent implementation using `core::simd`, built with the `nightly`
+    /// feature.
+    Portable,
+    /// The `riscv64` implementation using RVV 1.0 vector instructions, built with the `nightly`
+    /// feature.
+    Rvv,
+    /// The portable scalar implementation processing 8 bytes per 64-bit word, available on every
+    /// target.
+    Swar,
+}
+
+pub(crate) type UpdateFn = unsafe fn(u32, u32, &[u8]) -> (u32, u32);
+pub(crate) type UpdateX4Fn = unsafe fn([(u32, u32); 4], [&[u8]; 4]) -> [(u32, u32); 4];
+pub(crate) type UpdateCopyFn = unsafe fn(u32, u32, &mut [u8], &[u8]) -> (u32, u32);
+pub(crate) type UpdateCrc32Fn = unsafe fn(u32, u32, u32, &[u8]) -> (u32, u32, u32);
+pub(crate) type Crc32Fn = unsafe fn(u32, &[u8]) -> u32;
+
+/// The entry points of a single implementation.
+///
+/// Kernels are only handed out by `Backend::kernel` and `specialized::detect` once the CPU is
+/// known to support them, so calling `update` through a kernel reference is always sound.
+#[derive(Debug)]
+pub(crate) struct Kernel {
+    pub(crate) backend: Backend,
+    pub(crate) update: UpdateFn,
+    /// Updates four independent states at once, interleaving their buffers to hide latency.
+    pub(crate) update_x4: Option<UpdateX4Fn>,
+    /// Copies the second buffer into the first one while updating the state with it. Both
+    /// buffers must have the same length.
+    pub(crate) update_copy: Option<UpdateCopyFn>,
+    /// Updates the state and a CRC-32 in a single pass. Besides the backend, this needs the
+    /// carry-less multiplication checked by `specialized::detect_crc32`.
+    pub(crate) update_crc32: Option<UpdateCrc32Fn>,
+}
+
+// All backends, from most to least preferred.
+const ALL: [Backend; 11] = [
+    Backend::Avx512Vnni,
+    Backend::Avx512Bw,
+    Backend::Avx2,
+    Backend::Ssse3,
+    Backend::Sse2,
+    Backend::Neon,
+    Backend::Simd128,
+    Backend::Rvv,
+    Backend::Portable,
+    #[cfg(target_pointer_width = "64")]
+    Backend::Swar,
+    Backend::Baseline,
+    // The 64-bit words are split into 32-bit halves on other targets.
+    #[cfg(not(target_pointer_width = "64"))]
+    Backend::Swar,
+];
+
+impl Backend {
+    /// Return the backends supported by the current CPU, from most to least preferred.
+    ///
+    /// The first backend returned is the one selected by `Adler32::new`, unless AVX2 or AVX-512
+    /// was enabled at compile time and selected statically.
+    pub fn available() -> impl Iterator<Item = Backend> {
+        ALL.iter().copied().filter(|backend| backend.is_available())
+    }
+
+    /// Indicates whether this backend is supported by the current CPU.
+    pub fn is_available(self) -> bool {
+        self.kernel().is_some()
+    }
+
+    pub(crate) fn kernel(self) -> Option<&'static Kernel> {
+        match self {
+            Backend::Baseline => Some(&crate::baseline::KERNEL),
+            Backend::Swar => Some(&crate::baseline::swar::KERNEL),
+            #[cfg(feature = "nightly")]
+            Backend::Portable => Some(&crate::specialized::PORTABLE),
+            backend => crate::specialized::kernel(backend),
+        }
+    }
+
+    /// Return the name of this backend.
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Baseline => "baseline",
+            Backend::Ssse3 => "ssse3",
+            Backend::Avx2 => "avx2",
+            Backend::Avx512Bw => "avx512bw",
+            Backend::Avx512Vnni => "avx512vnni",
+            Backend::Sse2 => "sse2",
+            Backend::Neon => "neon",
+            Backend::Simd128 => "simd128",
+            Backend::Portable => "portable",
+            Backend::Rvv => "rvv",
+            Backend::Swar => "swar",
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Backend, ALL};
+    use crate::Adler32;
+
+    #[test]
+    fn baseline_is_always_available() {
+        assert!(Backend::Baseline.is_available());
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!(Backend::available().last(), Some(Backend::Baseline));
+        #[cfg(not(target_pointer_width = "64"))]
+        assert_eq!(Backend::available().last(), Some(Backend::Swar));
+    }
+
+    // AVX2 or AVX-512 enabled at compile time is selected even if the CPU supports a better one.
+    #[test]
+    #[cfg(not(target_feature = "avx2"))]
+    fn new_selects_first_available_backend() {
+        assert_eq!(Some(Adler32::new().backend()), Backend::available().next());
+    }
+
+    #[test]
+    fn detection_is_cached() {
+        assert!(core::ptr::eq(Adler32::new().kernel, Adler32::new().kernel));
+        assert!(core::ptr::eq(
+            Adler32::new().kernel,
+            crate::specialized::detect()
+        ));
+    }
+
+    #[test]
+    #[cfg(all(target_feature = "avx512f", target_feature = "avx512bw"))]
+    fn avx512_is_selected_statically() {
+        let expected = if cfg!(target_feature = "avx512vnni") {
+            Backend::Avx512Vnni
+        } else {
+            Backend::Avx512Bw
+        };
+        assert_eq!(Adler32::new().backend(), expected);
+    }
+
+    #[test]
+    #[cfg(all(
+        target_feature = "avx2",
+        not(all(target_feature = "avx512f", target_feature = "avx512bw"))
+    ))]
+    fn avx2_is_selected_statically() {
+        assert_eq!(Adler32::new().backend(), Backend::Avx2);
+    }
+
+    // SSSE3 enabled at compile time only sets the floor of the runtime detection.
+    #[test]
+    #[cfg(all(target_feature = "ssse3", not(target_feature = "avx2")))]
+    fn ssse3_still_detects_higher_tiers() {
+        let detected = crate::specialized::detect().backend;
+        assert_eq!(Some(detected), Backend::available().next());
+        if Backend::Avx2.is_available() {
+            assert_ne!(detected, Backend::Ssse3);
+        }
+    }
+
+    #[test]
+    fn with_backend_is_pinned() {
+        for &backend in &ALL {
+            match Adler32::with_backend(backend, 1) {
+                Some(adler32) => {
+                    assert!(backend.is_available());
+                    assert_eq!(adler32.backend(), backend);
+                    assert_eq!(
+                        adler32.is_simd_enabled(),
+                        !matches!(backend, Backend::Baseline | Backend::Swar)
+                    );
+                }
+                None => assert!(!backend.is_available()),
+            }
+        }
+    }
+
+    quickcheck::quickcheck! {
+        fn backends_are_consistent(init: u32, buf: Vec<u8>) -> bool {
+            let mut expected = Adler32::with_backend(Backend::Baseline, init).unwrap();
+            expected.update(&buf);
+            Backend::available().all(|backend| {
+                let mut actual = Adler32::with_backend(backend, init).unwrap();
+                actual.update(&buf);
+                actual.as_u32() == expected.as_u32()
+            })
+        }
+    }
+}
@@ -1,7 +1,21 @@
+use crate::backend::Kernel;
+use crate::Backend;
+
+pub mod swar;
+
 pub(crate) const BASE: u32 = 65521;
-const NMAX: usize = 5552;
+// The sums are kept in 64 bits, so they only need reducing every 16 MiB.
+const NMAX: usize = 1 << 24;
 const CHUNK_SIZE: usize = 16;
 
+pub(crate) static KERNEL: Kernel = Kernel {
+    backend: Backend::Baseline,
+    update: update_fast,
+    update_x4: None,
+    update_copy: Some(update_copy),
+    update_crc32: None,
+};
+
 #[derive(Copy, Clone, Debug)]
 pub struct State {
     state: (u32, u32),
@@ -27,51 +41,192 @@ impl State {
     }
 }
 
+// Process a short input, or the tail left over by a block-based implementation. This loads 8
+// bytes at a time, so it is much cheaper than a byte loop for the sizes involved.
 #[inline(always)]
-pub(crate) fn update_slow(mut a: u32, mut b: u32, buf: &[u8]) -> (u32, u32) {
-    for &byte in buf {
-        a += u32::from(byte);
-        b += a;
+pub(crate) fn update_tail(mut a: u32, mut b: u32, buf: &[u8]) -> (u32, u32) {
+    let words = buf.chunks_exact(swar::WORD_SIZE);
+    let remainder = words.remainder();
+    for word in words {
+        let (sum, weighted) = swar::sums(swar::load(word));
+        b += (a << 3) + weighted as u32;
+        a += sum as u32;
     }
-    (a % BASE, b % BASE)
+    let len = remainder.len();
+    if len != 0 {
+        // The last bytes are loaded as a word ending at the end of `buf`, so their weights still
+        // count down to 1. When `buf` is long enough, the word overlaps the previous one and the
+        // bytes processed already are masked out.
+        let word = if buf.len() >= swar::WORD_SIZE {
+            swar::load(&buf[buf.len() - swar::WORD_SIZE..])
+                & (u64::MAX << (8 * (swar::WORD_SIZE - len)))
+        } else {
+            swar::load_partial(remainder)
+        };
+        let (sum, weighted) = swar::sums(word);
+        b += a * len as u32 + weighted as u32;
+        a += sum as u32;
+    }
+    (reduce(a), reduce(b))
+}
+
+// Reduce `x` modulo `BASE`. As 2^16 = 15 (mod BASE), folding the high half into the low one
+// twice leaves less than 2 * BASE, so a single conditional subtraction finishes the job.
+#[inline(always)]
+pub(crate) fn reduce(x: u32) -> u32 {
+    let x = (x >> 16) * 15 + (x & 0xffff);
+    let x = (x >> 16) * 15 + (x & 0xffff);
+    if x >= BASE {
+        x - BASE
+    } else {
+        x
+    }
+}
+
+// Reduce `x` modulo `BASE` using 2^32 = 225 (mod BASE), for any 64-bit input.
+#[inline(always)]
+pub(crate) fn reduce_wide(x: u64) -> u32 {
+    // Each fold leaves less than 2^41, then 2^32 + 2^17, then 2^21.
+    let x = (x >> 32) * 225 + (x & 0xffff_ffff);
+    let x = (x >> 32) * 225 + (x & 0xffff_ffff);
+    let x = (x >> 16) * 15 + (x & 0xffff);
+    reduce(x as u32)
 }
 
-fn update_fast<'a>(mut a: u32, mut b: u32, buf: &'a [u8]) -> (u32, u32) {
+fn update_fast(a: u32, b: u32, buf: &[u8]) -> (u32, u32) {
+    let mut a = u64::from(a);
+    let mut b = u64::from(b);
     let chunks = buf.chunks_exact(NMAX);
     let mut remainder = chunks.remainder();
     for chunk in chunks {
-        add_reduce(&mut a, &mut b, chunk);
-        a %= BASE;
-        b %= BASE;
+        add_reduce(&mut a, &mut b, chunk, None);
+        a = u64::from(reduce_wide(a));
+        b = u64::from(reduce_wide(b));
     }
-    remainder = add_reduce(&mut a, &mut b, remainder);
-    update_slow(a, b, remainder)
+    remainder = add_reduce(&mut a, &mut b, remainder, None);
+    update_tail(reduce_wide(a), reduce_wide(b), remainder)
 }
 
+fn update_copy(a: u32, b: u32, dst: &mut [u8], src: &[u8]) -> (u32, u32) {
+    let mut a = u64::from(a);
+    let mut b = u64::from(b);
+    let chunks = src.chunks_exact(NMAX);
+    let mut remainder = chunks.remainder();
+    let mut dst_chunks = dst.chunks_exact_mut(NMAX);
+    for (chunk, dst_chunk) in chunks.zip(&mut dst_chunks) {
+        add_reduce(&mut a, &mut b, chunk, Some(dst_chunk));
+        a = u64::from(reduce_wide(a));
+        b = u64::from(reduce_wide(b));
+    }
+    let dst = dst_chunks.into_remainder();
+    remainder = add_reduce(&mut a, &mut b, remainder, Some(&mut *dst));
+    let copied = dst.len() - remainder.len();
+    dst[copied..].copy_from_slice(remainder);
+    update_tail(reduce_wide(a), reduce_wide(b), remainder)
+}
+
+// When `dst` is given, each block is also copied to the same offset in it.
 #[inline(always)]
-fn add_reduce<'a>(a: &mut u32, b: &mut u32, chunk: &'a [u8]) -> &'a [u8] {
+fn add_reduce<'a>(
+    a: &mut u64,
+    b: &mut u64,
+    chunk: &'a [u8],
+    mut dst: Option<&mut [u8]>,
+) -> &'a [u8] {
     if chunk.len() < CHUNK_SIZE {
         return chunk;
     }
     let inner_chunks = chunk.chunks_exact(CHUNK_SIZE);
     let remainder = inner_chunks.remainder();
-    for inner_chunk in inner_chunks {
+    for (i, inner_chunk) in inner_chunks.enumerate() {
+        if let Some(dst) = dst.as_deref_mut() {
+            dst[i * CHUNK_SIZE..][..CHUNK_SIZE].copy_from_slice(inner_chunk);
+        }
         update_16(a, b, inner_chunk);
     }
     remainder
 }
 
 #[inline(always)]
-fn update_16(a: &mut u32, b: &mut u32, buf: &[u8]) {
+fn update_16(a: &mut u64, b: &mut u64, buf: &[u8]) {
     debug_assert!(buf.len() >= 16);
-    for i in 0..16 {
-        *a += u32::from(buf[i]);
+    for &byte in &buf[..16] {
+        *a += u64::from(byte);
         *b += *a;
     }
 }
 
 #[cfg(test)]
 mod tests {
+    quickcheck::quickcheck! {
+        fn reduce_is_same_as_modulo(x: u32) -> bool {
+            super::reduce(x) == x % super::BASE
+        }
+
+        fn reduce_wide_is_same_as_modulo(x: u64) -> bool {
+            u64::from(super::reduce_wide(x)) == x % u64::from(super::BASE)
+        }
+
+        fn tail_is_same_as_reference(init: u32, buf: Vec<u8>) -> bool {
+            let buf = &buf[..buf.len().min(64)];
+            let mut expected = adler32::RollingAdler32::from_value(init);
+            expected.update_buffer(buf);
+            let (a, b) = super::update_tail(init & 0xffff, init >> 16, buf);
+            expected.hash() == a | (b << 16)
+        }
+    }
+
+    #[test]
+    fn reduce_is_valid_for_edge_cases() {
+        let base = super::BASE;
+        for &x in &[
+            0,
+            1,
+            base - 1,
+            base,
+            base + 1,
+            2 * base - 1,
+            2 * base,
+            u32::MAX,
+        ] {
+            assert_eq!(super::reduce(x), x % base, "{}", x);
+        }
+        let base = u64::from(base);
+        for &x in &[
+            0,
+            base - 1,
+            base,
+            1 << 32,
+            (1 << 41) - 1,
+            u64::MAX - 1,
+            u64::MAX,
+        ] {
+            assert_eq!(u64::from(super::reduce_wide(x)), x % base, "{}", x);
+        }
+    }
+
+    #[test]
+    fn baseline_is_valid_for_large_input() {
+        let v = vec![0xff; super::NMAX + 13];
+        let mut expected = adler32::RollingAdler32::from_value(0xfff0_fff0);
+        let mut actual = super::State::new(0xfff0_fff0);
+        expected.update_buffer(&v);
+        actual.update(&v);
+        assert_eq!(expected.hash(), actual.finalize())
+    }
+
+    #[test]
+    fn tail_is_valid_for_every_short_length() {
+        let max = super::BASE - 1;
+        for len in 0..=64 {
+            let buf = vec![0xff; len];
+            let mut expected = adler32::RollingAdler32::from_value(max | (max << 16));
+            expected.update_buffer(&buf);
+            let (a, b) = super::update_tail(max, max, &buf);
+            assert_eq!(expected.hash(), a | (b << 16), "length {}", len);
+        }
+    }
+
     #[test]
     fn baseline_is_valid() {
         fn golden(expected: u32, input: &[u8]) {
@@ -0,0 +1,171 @@
+#[cfg(not(feature = "std"))]
+use core::convert::TryInto;
+#[cfg(feature = "std")]
+use std::convert::TryInto;
+
+use super::reduce_wide;
+use crate::backend::Kernel;
+use crate::Backend;
+
+// The sums are kept in 64 bits, so they only need reducing every 16 MiB instead of every
+// 5552 bytes.
+const NMAX: usize = 1 << 24;
+pub(super) const WORD_SIZE: usize = 8;
+
+// Selects the even bytes of a word, widened to 16-bit lanes.
+const EVEN_BYTES: u64 = 0x00ff_00ff_00ff_00ff;
+// Multiplying 16-bit lanes by these accumulates their (weighted) sum in the top lane. The
+// lanes hold at most 510, so none of the partial sums carry into the next lane.
+const SUM: u64 = 0x0001_0001_0001_0001;
+const WEIGHTS_EVEN: u64 = 0x0008_0006_0004_0002;
+const WEIGHTS_ODD: u64 = 0x0007_0005_0003_0001;
+
+pub(crate) static KERNEL: Kernel = Kernel {
+    backend: Backend::Swar,
+    update,
+    update_x4: None,
+    update_copy: None,
+    update_crc32: None,
+};
+
+#[derive(Copy, Clone, Debug)]
+pub struct State {
+    state: (u32, u32),
+}
+
+impl State {
+    pub fn new(initial: u32) -> Self {
+        Self {
+            state: (initial & 0xffff, initial >> 16),
+        }
+    }
+
+    pub fn finalize(&self) -> u32 {
+        self.state.0 | (self.state.1 << 16)
+    }
+
+    pub fn reset(&mut self) {
+        self.state = (1, 0);
+    }
+
+    pub fn update(&mut self, buf: &[u8]) {
+        self.state = update(self.state.0, self.state.1, buf);
+    }
+}
+
+fn update(a: u32, b: u32, buf: &[u8]) -> (u32, u32) {
+    let mut a = u64::from(a);
+    let mut b = u64::from(b);
+    let chunks = buf.chunks_exact(NMAX);
+    let mut remainder = chunks.remainder();
+    for chunk in chunks {
+        add_reduce(&mut a, &mut b, chunk);
+        a = u64::from(reduce_wide(a));
+        b = u64::from(reduce_wide(b));
+    }
+    remainder = add_reduce(&mut a, &mut b, remainder);
+    for &byte in remainder {
+        a += u64::from(byte);
+        b += a;
+    }
+    (reduce_wide(a), reduce_wide(b))
+}
+
+#[inline(always)]
+fn add_reduce<'a>(a: &mut u64, b: &mut u64, chunk: &'a [u8]) -> &'a [u8] {
+    // Two words are processed per iteration, which shortens the dependency chain on `a`.
+    let pairs = chunk.chunks_exact(2 * WORD_SIZE);
+    let mut remainder = pairs.remainder();
+    for pair in pairs {
+        let (sum_lo, weighted_lo) = sums(load(&pair[..WORD_SIZE]));
+        let (sum_hi, weighted_hi) = sums(load(&pair[WORD_SIZE..]));
+        *b += (*a << 4) + (sum_lo << 3) + weighted_lo + weighted_hi;
+        *a += sum_lo + sum_hi;
+    }
+    if remainder.len() >= WORD_SIZE {
+        let (sum, weighted) = sums(load(remainder));
+        *b += (*a << 3) + weighted;
+        *a += sum;
+        remainder = &remainder[WORD_SIZE..];
+    }
+    remainder
+}
+
+// Load the first 8 bytes of `buf` as a little-endian word.
+#[inline(always)]
+pub(super) fn load(buf: &[u8]) -> u64 {
+    u64::from_le_bytes(buf[..WORD_SIZE].try_into().unwrap())
+}
+
+// Load the 1 to 7 bytes of `buf` into the top bytes of a word, without reading past its end.
+// The overlapping loads place shared bytes at the same position, so they can be merged with `|`.
+#[inline(always)]
+pub(super) fn load_partial(buf: &[u8]) -> u64 {
+    let len = buf.len();
+    debug_assert!(len > 0 && len < WORD_SIZE);
+    let shift = 8 * (WORD_SIZE - len) as u32;
+    if len >= 4 {
+        let first = u32::from_le_bytes(buf[..4].try_into().unwrap());
+        let last = u32::from_le_bytes(buf[len - 4..].try_into().unwrap());
+        (u64::from(first) << shift) | (u64::from(last) << 32)
+    } else {
+        // The first, middle and last bytes cover every byte of a 1 to 3 byte slice.
+        let middle = len / 2;
+        (u64::from(buf[0]) << shift)
+            | (u64::from(buf[middle]) << (shift + 8 * middle as u32))
+            | (u64::from(buf[len - 1]) << 56)
+    }
+}
+
+// Return the sum of the bytes in `word`, and their sum weighted from 8 down to 1.
+#[inline(always)]
+pub(super) fn sums(word: u64) -> (u64, u64) {
+    let even = word & EVEN_BYTES;
+    let odd = (word >> 8) & EVEN_BYTES;
+    let sum = (even + odd).wrapping_mul(SUM) >> 48;
+    let weighted = even
+        .wrapping_mul(WEIGHTS_EVEN)
+        .wrapping_add(odd.wrapping_mul(WEIGHTS_ODD))
+        >> 48;
+    (sum, weighted)
+}
+
+#[cfg(test)]
+mod tests {
+    quickcheck::quickcheck! {
+        fn swar_is_same_as_baseline(init: u32, buf: Vec<u8>) -> bool {
+            let mut expected = crate::baseline::State::new(init);
+            let mut actual = super::State::new(init);
+            expected.update(&buf);
+            actual.update(&buf);
+            expected.finalize() == actual.finalize()
+        }
+
+        fn swar_supports_random_alignment(init: u32, chunks: Vec<(Vec<u8>, usize)>) -> bool {
+            let mut expected = adler32::RollingAdler32::from_value(init);
+            let mut actual = super::State::new(init);
+            for (chunk, mut offset) in chunks {
+                // Simulate random alignments by offsetting the slice by up to 15 bytes
+                offset &= 0xf;
+                if chunk.len() <= offset {
+                    expected.update_buffer(&chunk);
+                    actual.update(&chunk);
+                } else {
+                    expected.update_buffer(&chunk[offset..]);
+                    actual.update(&chunk[offset..]);
+                }
+            }
+            expected.hash() == actual.finalize()
+        }
+    }
+
+    #[test]
+    fn swar_is_valid_for_large_input() {
+        let v = vec![0xff; super::NMAX + 13];
+        let mut expected = crate::baseline::State::new(0xfff0_fff0);
+        let mut actual = super::State::new(0xfff0_fff0);
+        expected.update(&v);
+        actual.update(&v);
+        assert_eq!(expected.finalize(), actual.finalize())
+    }
+}
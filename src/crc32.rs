@@ -0,0 +1,236 @@
+use crate::backend::Crc32Fn;
+use crate::{baseline, specialized, Adler32, Backend, SHORT_INPUT_LEN};
+
+// The bit-reflected CRC-32 (IEEE) polynomial.
+const POLY: u32 = 0xedb8_8320;
+// Without a fused kernel, the input is processed in blocks of this size so that the second pass
+// over each block reads it from cache.
+const BLOCK_LEN: usize = 4096;
+
+// `TABLES[k][i]` is the CRC of byte `i` followed by `k` zero bytes, for slicing by 8.
+const TABLES: [[u32; 256]; 8] = tables();
+
+const fn tables() -> [[u32; 256]; 8] {
+    let mut tables = [[0; 256]; 8];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ POLY
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        tables[0][i] = crc;
+        i += 1;
+    }
+    let mut i = 0;
+    while i < 256 {
+        let mut k = 1;
+        while k < 8 {
+            let prev = tables[k - 1][i];
+            tables[k][i] = (prev >> 8) ^ tables[0][(prev & 0xff) as usize];
+            k += 1;
+        }
+        i += 1;
+    }
+    tables
+}
+
+pub(crate) fn update_scalar(crc: u32, buf: &[u8]) -> u32 {
+    let mut crc = !crc;
+    let words = buf.chunks_exact(8);
+    let remainder = words.remainder();
+    for word in words {
+        let lo = u32::from_le_bytes([word[0], word[1], word[2], word[3]]) ^ crc;
+        let hi = u32::from_le_bytes([word[4], word[5], word[6], word[7]]);
+        crc = TABLES[7][(lo & 0xff) as usize]
+            ^ TABLES[6][((lo >> 8) & 0xff) as usize]
+            ^ TABLES[5][((lo >> 16) & 0xff) as usize]
+            ^ TABLES[4][(lo >> 24) as usize]
+            ^ TABLES[3][(hi & 0xff) as usize]
+            ^ TABLES[2][((hi >> 8) & 0xff) as usize]
+            ^ TABLES[1][((hi >> 16) & 0xff) as usize]
+            ^ TABLES[0][(hi >> 24) as usize];
+    }
+    for &byte in remainder {
+        crc = (crc >> 8) ^ TABLES[0][((crc ^ u32::from(byte)) & 0xff) as usize];
+    }
+    !crc
+}
+
+#[derive(Copy, Clone, Debug)]
+/// Computes an Adler-32 and a CRC-32 (IEEE) of the same data in a single pass.
+///
+/// With AVX2 or SSSE3 and PCLMULQDQ, both checksums are computed by the same loop over the data,
+/// even if a faster backend would be selected for Adler-32 alone. Otherwise, each block of the
+/// input is checksummed twice while it is in cache.
+///
+/// Known limitation: only the SSSE3 loop folds the CRC-32 from the vectors it loaded for the
+/// Adler-32. The AVX2 loop loads each 128-byte group a second time from L1 for the CRC-32,
+/// because splitting its 256-bit vectors measured slower than the reload.
+///
+/// ```
+/// use adler32fast::Adler32Crc32;
+///
+/// let mut hasher = Adler32Crc32::new();
+/// hasher.update(b"foo bar baz");
+///
+/// assert_eq!(hasher.adler32(), 0x17ba03f7);
+/// assert_eq!(hasher.crc32(), 0xf262de61);
+/// ```
+pub struct Adler32Crc32 {
+    adler32: Adler32,
+    crc32: u32,
+    crc32_fn: Option<Crc32Fn>,
+}
+
+impl Adler32Crc32 {
+    /// Create a new `Adler32Crc32`.
+    pub fn new() -> Self {
+        Self::with_initial(1, 0)
+    }
+
+    /// Create a new `Adler32Crc32` that resumes from the given checksums.
+    pub fn with_initial(adler32: u32, crc32: u32) -> Self {
+        let crc32_fn = specialized::detect_crc32();
+        let mut kernel = specialized::detect();
+        if crc32_fn.is_some() && kernel.update_crc32.is_none() {
+            // Checksumming each block twice is slower than a fused loop, even with a backend
+            // that is faster for Adler-32 alone.
+            if let Some(fused) = Backend::available()
+                .filter_map(Backend::kernel)
+                .find(|kernel| kernel.update_crc32.is_some())
+            {
+                kernel = fused;
+            }
+        }
+        Self {
+            adler32: Adler32::with_kernel(kernel, adler32),
+            crc32,
+            crc32_fn,
+        }
+    }
+
+    /// Return the computed Adler-32 value.
+    pub fn adler32(&self) -> u32 {
+        self.adler32.as_u32()
+    }
+
+    /// Return the computed CRC-32 value.
+    pub fn crc32(&self) -> u32 {
+        self.crc32
+    }
+
+    /// Reset the hash state.
+    pub fn reset(&mut self) {
+        self.adler32.reset();
+        self.crc32 = 0;
+    }
+
+    /// Process the given byte slice and update both checksums.
+    pub fn update(&mut self, buf: &[u8]) {
+        if buf.len() < SHORT_INPUT_LEN {
+            let (a, b) = self.adler32.state;
+            self.adler32.state = baseline::update_tail(a, b, buf);
+            self.crc32 = update_scalar(self.crc32, buf);
+            return;
+        }
+        match (self.crc32_fn, self.adler32.kernel.update_crc32) {
+            (Some(_), Some(update_crc32)) => {
+                let (a, b) = self.adler32.state;
+                // SAFETY: Kernels are only handed out for backends supported by the CPU, and
+                // `crc32_fn` is only set when carry-less multiplication is supported as well.
+                let (a, b, crc32) = unsafe { update_crc32(a, b, self.crc32, buf) };
+                self.adler32.state = (a, b);
+                self.crc32 = crc32;
+            }
+            (crc32_fn, _) => {
+                for block in buf.chunks(BLOCK_LEN) {
+                    self.adler32.update(block);
+                    self.crc32 = match crc32_fn {
+                        // SAFETY: `detect_crc32` only returns implementations supported by the
+                        // CPU.
+                        Some(crc32_fn) => unsafe { crc32_fn(self.crc32, block) },
+                        None => update_scalar(self.crc32, block),
+                    };
+                }
+            }
+        }
+    }
+}
+
+impl Default for Adler32Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Adler32Crc32;
+    use crate::{Adler32, Backend};
+
+    fn reference(buf: &[u8]) -> (u32, u32) {
+        let mut adler32 = adler32::RollingAdler32::new();
+        adler32.update_buffer(buf);
+        (adler32.hash(), crc32fast::hash(buf))
+    }
+
+    quickcheck::quickcheck! {
+        fn scalar_is_same_as_reference(init: u32, buf: Vec<u8>) -> bool {
+            let mut expected = crc32fast::Hasher::new_with_initial(init);
+            expected.update(&buf);
+            expected.finalize() == super::update_scalar(init, &buf)
+        }
+
+        fn adler32_crc32_is_same_as_reference(chunks: Vec<Vec<u8>>) -> bool {
+            let mut hasher = Adler32Crc32::new();
+            for chunk in &chunks {
+                hasher.update(chunk);
+            }
+            (hasher.adler32(), hasher.crc32()) == reference(&chunks.concat())
+        }
+    }
+
+    #[test]
+    fn adler32_crc32_is_valid_for_every_backend() {
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i * 31 % 251) as u8).collect();
+        let crc32_fns = [None, crate::specialized::detect_crc32()];
+        for backend in Backend::available() {
+            for &crc32_fn in &crc32_fns {
+                for &len in &[0, 31, 32, 127, 191, 192, 5567, 5568, 70_000, 100_000] {
+                    let mut hasher = Adler32Crc32 {
+                        adler32: Adler32::with_backend(backend, 1).unwrap(),
+                        crc32: 0,
+                        crc32_fn,
+                    };
+                    hasher.update(&data[..len]);
+                    assert_eq!(
+                        (hasher.adler32(), hasher.crc32()),
+                        reference(&data[..len]),
+                        "{} {}",
+                        backend,
+                        len
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn adler32_crc32_is_valid_for_worst_case_input() {
+        let data = vec![0xff; 100_000];
+        let mut hasher = Adler32Crc32::with_initial(0xfff0_fff0, 0xdead_beef);
+        hasher.update(&data);
+        let mut adler32 = adler32::RollingAdler32::from_value(0xfff0_fff0);
+        adler32.update_buffer(&data);
+        let mut crc32 = crc32fast::Hasher::new_with_initial(0xdead_beef);
+        crc32.update(&data);
+        assert_eq!(hasher.adler32(), adler32.hash());
+        assert_eq!(hasher.crc32(), crc32.finalize());
+    }
+}
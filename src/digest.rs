@@ -0,0 +1,70 @@
+use ::digest::{
+    consts::U4, FixedOutput, FixedOutputReset, HashMarker, Output, OutputSizeUser, Reset, Update,
+};
+
+use crate::Adler32;
+
+impl HashMarker for Adler32 {}
+
+impl OutputSizeUser for Adler32 {
+    type OutputSize = U4;
+}
+
+impl Update for Adler32 {
+    fn update(&mut self, data: &[u8]) {
+        Adler32::update(self, data);
+    }
+}
+
+impl FixedOutput for Adler32 {
+    fn finalize_into(self, out: &mut Output<Self>) {
+        out.copy_from_slice(&self.as_u32().to_be_bytes());
+    }
+}
+
+impl FixedOutputReset for Adler32 {
+    fn finalize_into_reset(&mut self, out: &mut Output<Self>) {
+        out.copy_from_slice(&self.as_u32().to_be_bytes());
+        Adler32::reset(self);
+    }
+}
+
+impl Reset for Adler32 {
+    fn reset(&mut self) {
+        Adler32::reset(self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::digest::Digest;
+
+    use crate::Adler32;
+
+    fn digest<D: Digest>(data: &[u8]) -> Vec<u8> {
+        let mut hasher = D::new();
+        hasher.update(data);
+        hasher.finalize().to_vec()
+    }
+
+    quickcheck::quickcheck! {
+        fn digest_is_big_endian_checksum(buf: Vec<u8>) -> bool {
+            let mut adler32 = Adler32::new();
+            adler32.update(&buf);
+            digest::<Adler32>(&buf) == adler32.as_u32().to_be_bytes()
+        }
+    }
+
+    #[test]
+    fn digest_supports_reset() {
+        let mut hasher = <Adler32 as Digest>::new();
+        Digest::update(&mut hasher, b"foo bar baz");
+        let first = hasher.finalize_reset();
+        Digest::update(&mut hasher, b"foo bar baz");
+        assert_eq!(first, hasher.finalize());
+        assert_eq!(
+            <Adler32 as Digest>::digest(b"Wikipedia")[..],
+            [0x11, 0xe6, 0x03, 0x98]
+        );
+    }
+}
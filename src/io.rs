@@ -0,0 +1,348 @@
+use std::io::{self, Read, Write};
+
+use crate::{Adler32, ChecksumMismatch};
+
+const TRAILER_LEN: usize = 4;
+
+/// A reader adapter which computes the Adler-32 checksum of all data read through it.
+#[derive(Debug)]
+pub struct Adler32Reader<R> {
+    inner: R,
+    adler32: Adler32,
+    byte_count: u64,
+}
+
+impl<R> Adler32Reader<R> {
+    /// Create a new `Adler32Reader` wrapping the given reader.
+    pub fn new(inner: R) -> Self {
+        Self::with_adler32(inner, Adler32::new())
+    }
+
+    /// Create a new `Adler32Reader` which continues an in-progress computation.
+    pub fn with_adler32(inner: R, adler32: Adler32) -> Self {
+        Self {
+            inner,
+            adler32,
+            byte_count: 0,
+        }
+    }
+
+    /// Return the Adler-32 value of the data read so far.
+    pub fn checksum(&self) -> u32 {
+        self.adler32.as_u32()
+    }
+
+    /// Return the number of bytes read so far.
+    pub fn byte_count(&self) -> u64 {
+        self.byte_count
+    }
+
+    /// Return a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Return a mutable reference to the underlying reader.
+    ///
+    /// Data read directly from the underlying reader is not included in the checksum.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Consume the adapter, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for Adler32Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.adler32.update(&buf[..n]);
+        self.byte_count += n as u64;
+        Ok(n)
+    }
+}
+
+/// A writer adapter which computes the Adler-32 checksum of all data written through it.
+#[derive(Debug)]
+pub struct Adler32Writer<W> {
+    inner: W,
+    adler32: Adler32,
+    byte_count: u64,
+}
+
+impl<W> Adler32Writer<W> {
+    /// Create a new `Adler32Writer` wrapping the given writer.
+    pub fn new(inner: W) -> Self {
+        Self::with_adler32(inner, Adler32::new())
+    }
+
+    /// Create a new `Adler32Writer` which continues an in-progress computation.
+    pub fn with_adler32(inner: W, adler32: Adler32) -> Self {
+        Self {
+            inner,
+            adler32,
+            byte_count: 0,
+        }
+    }
+
+    /// Return the Adler-32 value of the data written so far.
+    pub fn checksum(&self) -> u32 {
+        self.adler32.as_u32()
+    }
+
+    /// Return the number of bytes written so far.
+    pub fn byte_count(&self) -> u64 {
+        self.byte_count
+    }
+
+    /// Return a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Return a mutable reference to the underlying writer.
+    ///
+    /// Data written directly to the underlying writer is not included in the checksum.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Consume the adapter, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for Adler32Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.adler32.update(&buf[..n]);
+        self.byte_count += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A reader adapter which verifies a trailing big-endian Adler-32 checksum.
+///
+/// The final 4 bytes of the underlying reader are held back and treated as the expected
+/// checksum of all preceding bytes, as in the zlib container format. Only the payload is
+/// returned to the caller. Once the underlying reader reaches EOF, the checksum is verified,
+/// and a mismatch is reported as an `io::ErrorKind::InvalidData` error wrapping a
+/// [`ChecksumMismatch`].
+#[derive(Debug)]
+pub struct Adler32VerifyingReader<R> {
+    inner: R,
+    adler32: Adler32,
+    byte_count: u64,
+    held: [u8; TRAILER_LEN],
+    held_len: usize,
+    verified: bool,
+}
+
+impl<R> Adler32VerifyingReader<R> {
+    /// Create a new `Adler32VerifyingReader` wrapping the given reader.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            adler32: Adler32::new(),
+            byte_count: 0,
+            held: [0; TRAILER_LEN],
+            held_len: 0,
+            verified: false,
+        }
+    }
+
+    /// Return the Adler-32 value of the payload returned so far.
+    pub fn checksum(&self) -> u32 {
+        self.adler32.as_u32()
+    }
+
+    /// Return the number of payload bytes returned so far.
+    pub fn byte_count(&self) -> u64 {
+        self.byte_count
+    }
+
+    /// Indicates whether the trailer has been read and matched the payload.
+    pub fn is_verified(&self) -> bool {
+        self.verified
+    }
+
+    /// Return a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Consume the adapter, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn verify(&mut self) -> io::Result<()> {
+        if self.held_len < TRAILER_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "stream ended before the Adler-32 trailer",
+            ));
+        }
+        let expected = u32::from_be_bytes(self.held);
+        let actual = self.adler32.as_u32();
+        if expected != actual {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                ChecksumMismatch { expected, actual },
+            ));
+        }
+        self.verified = true;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Adler32VerifyingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.verified {
+            return Ok(0);
+        }
+        loop {
+            let n = self.inner.read(buf)?;
+            if n == 0 {
+                self.verify()?;
+                return Ok(0);
+            }
+
+            // The logical stream is `held ++ buf[..n]`; everything but its last 4 bytes can be
+            // returned to the caller.
+            let held_len = self.held_len;
+            if held_len + n <= TRAILER_LEN {
+                self.held[held_len..held_len + n].copy_from_slice(&buf[..n]);
+                self.held_len += n;
+                continue;
+            }
+            let emit = held_len + n - TRAILER_LEN;
+            let mut held = [0; TRAILER_LEN];
+            if n >= TRAILER_LEN {
+                held.copy_from_slice(&buf[n - TRAILER_LEN..n]);
+                buf.copy_within(..n - TRAILER_LEN, held_len);
+                buf[..held_len].copy_from_slice(&self.held[..held_len]);
+            } else {
+                let keep = held_len - emit;
+                held[..keep].copy_from_slice(&self.held[emit..held_len]);
+                held[keep..].copy_from_slice(&buf[..n]);
+                buf[..emit].copy_from_slice(&self.held[..emit]);
+            }
+            self.held = held;
+            self.held_len = TRAILER_LEN;
+
+            self.adler32.update(&buf[..emit]);
+            self.byte_count += emit as u64;
+            return Ok(emit);
+        }
+    }
+}
+
+impl Write for Adler32 {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read, Write};
+
+    use super::{Adler32Reader, Adler32VerifyingReader, Adler32Writer};
+    use crate::{Adler32, ChecksumMismatch};
+
+    fn expected(buf: &[u8]) -> u32 {
+        let mut adler32 = Adler32::new();
+        adler32.update(buf);
+        adler32.as_u32()
+    }
+
+    // Reads at most the given number of bytes at a time to exercise partial reads.
+    struct Trickle<'a>(&'a [u8], usize);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(self.0.len()).min(self.1);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    quickcheck::quickcheck! {
+        fn reader_is_same_as_update(buf: Vec<u8>) -> bool {
+            let mut reader = Adler32Reader::new(Trickle(&buf, 7));
+            let mut out = Vec::new();
+            reader.read_to_end(&mut out).unwrap();
+            out == buf
+                && reader.checksum() == expected(&buf)
+                && reader.byte_count() == buf.len() as u64
+        }
+
+        fn verifying_reader_accepts_valid_trailer(buf: Vec<u8>, max_read: u8) -> bool {
+            let mut stream = buf.clone();
+            stream.extend_from_slice(&expected(&buf).to_be_bytes());
+            let max_read = usize::from(max_read % 9) + 1;
+            let mut reader = Adler32VerifyingReader::new(Trickle(&stream, max_read));
+            let mut out = Vec::new();
+            reader.read_to_end(&mut out).unwrap();
+            out == buf && reader.is_verified() && reader.byte_count() == buf.len() as u64
+        }
+
+        fn writer_is_same_as_update(buf: Vec<u8>) -> bool {
+            let mut writer = Adler32Writer::new(Vec::new());
+            for chunk in buf.chunks(5) {
+                writer.write_all(chunk).unwrap();
+            }
+            writer.checksum() == expected(&buf)
+                && writer.byte_count() == buf.len() as u64
+                && writer.into_inner() == buf
+        }
+    }
+
+    #[test]
+    fn verifying_reader_rejects_invalid_trailer() {
+        let mut stream = b"foo bar baz".to_vec();
+        stream.extend_from_slice(&0xdead_beef_u32.to_be_bytes());
+        let mut reader = Adler32VerifyingReader::new(&stream[..]);
+        let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let mismatch = err.get_ref().unwrap().downcast_ref::<ChecksumMismatch>();
+        assert_eq!(
+            mismatch,
+            Some(&ChecksumMismatch {
+                expected: 0xdead_beef,
+                actual: expected(b"foo bar baz"),
+            })
+        );
+        assert!(!reader.is_verified());
+    }
+
+    #[test]
+    fn verifying_reader_rejects_truncated_stream() {
+        let mut reader = Adler32VerifyingReader::new(&b"foo"[..]);
+        let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn adler32_supports_io_copy() {
+        let buf = b"foo bar baz".repeat(1_000);
+        let mut adler32 = Adler32::new();
+        let n = io::copy(&mut &buf[..], &mut adler32).unwrap();
+        assert_eq!(n, buf.len() as u64);
+        assert_eq!(adler32.as_u32(), expected(&buf));
+    }
+}
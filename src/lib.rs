@@ -13,11 +13,61 @@
 //! This crate contains multiple Adler-32 implementations:
 //!
 //! - A fast baseline implementation which processes up to 16 bytes per iteration
-//! - An optimized implementation for modern `x86`/`x86_64` using SSE instructions
+//! - A scalar implementation which processes 8 bytes per 64-bit word, used on 64-bit targets
+//!   without one of the optimized implementations below
+//! - An optimized implementation for `x86`/`x86_64` using AVX-512 (BW/VNNI), AVX2, SSSE3 or
+//!   SSE2 instructions
+//! - An optimized implementation for `aarch64` using NEON instructions
+//! - An optimized implementation for `wasm32` using `simd128` instructions, when enabled at
+//!   compile time
+//! - An optimized implementation for `riscv64` using RVV 1.0 vector instructions, with the
+//!   `nightly` feature on a nightly toolchain
+//! - A target-independent implementation using `core::simd`, with the `nightly` feature on a
+//!   nightly toolchain, for targets without one of the above
 //!
-//! Calling the `Adler32::new`/`Adler32::from` constructors at runtime will perform a feature
-//! detection to select the most optimal implementation for the current CPU feature set.
+//! The first call to the `Adler32::new`/`Adler32::from` constructors at runtime will perform a
+//! feature detection to select the most optimal implementation for the current CPU feature set.
+//! The result is cached for the rest of the process, and each `update` dispatches through a
+//! single function pointer. Without the `std` feature, the detection queries `cpuid` directly.
+//!
+//! When the crate is built with `avx2` or AVX-512 enabled at compile time (for example with
+//! `-C target-cpu=native`), that implementation is selected statically instead, without any
+//! runtime detection. Lower tiers enabled at compile time, such as SSSE3 with
+//! `-C target-cpu=x86-64-v2`, are called directly when they are selected, but the higher tiers
+//! are still detected at runtime.
+//! `Adler32::backend` reports the selected implementation, and `Adler32::with_backend` pins a
+//! specific one.
+//!
+//! `checksum_many` and `checksum_many_into` checksum many independent buffers. Only the AVX2 and
+//! AVX-512 implementations interleave them, in groups of four, which hides latency for small
+//! inputs. The other implementations checksum the buffers one after another.
+//!
+//! `Adler32::update_copy` copies a buffer and checksums it in a single pass, for callers that
+//! would otherwise copy data into a window and then read it again.
+//!
+//! `Adler32Crc32` computes an Adler-32 and a CRC-32 of the same data. With PCLMULQDQ, the CRC-32
+//! is folded within the AVX2 or SSSE3 loop, so the data only passes through the cache once.
+//!
+//! With the `rayon` feature enabled, `Adler32::update_parallel` splits large inputs across the
+//! rayon thread pool and combines the partial checksums.
+//!
+//! With the `digest` feature enabled, `Adler32` implements the RustCrypto `digest` traits with a
+//! 4-byte big-endian output.
 #![cfg_attr(not(any(feature = "std", test)), no_std)]
+#![cfg_attr(feature = "nightly", feature(portable_simd))]
+#![cfg_attr(
+    all(feature = "nightly", target_arch = "riscv64"),
+    feature(riscv_target_feature)
+)]
+#![cfg_attr(
+    all(
+        feature = "nightly",
+        feature = "std",
+        target_arch = "riscv64",
+        not(target_feature = "v")
+    ),
+    feature(stdarch_riscv_feature_detection)
+)]
 
 // These are exported for benchmarking and fuzzing; not part of the API.
 #[doc(hidden)]
@@ -25,78 +75,204 @@ pub mod baseline;
 #[doc(hidden)]
 pub mod specialized;
 
+mod backend;
+mod crc32;
+#[cfg(feature = "digest")]
+mod digest;
+#[cfg(feature = "std")]
+mod io;
+mod many;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod rolling;
+pub mod zlib;
+
+pub use backend::Backend;
+use backend::Kernel;
+pub use crc32::Adler32Crc32;
+#[cfg(feature = "std")]
+pub use io::{Adler32Reader, Adler32VerifyingReader, Adler32Writer};
+#[cfg(feature = "std")]
+pub use many::checksum_many;
+pub use many::checksum_many_into;
+pub use rolling::RollingAdler32;
+
 #[cfg(not(feature = "std"))]
-use core::hash::Hasher;
+use core::{fmt, hash::Hasher};
 #[cfg(feature = "std")]
-use std::hash::Hasher;
+use std::{fmt, hash::Hasher};
 
 const DEFAULT_INIT_STATE: u32 = 1;
-
-#[derive(Copy, Clone, Debug)]
-enum State {
-    Baseline(baseline::State),
-    Specialized(specialized::State),
-}
+// Inputs shorter than this are checksummed inline, without dispatching to a backend.
+const SHORT_INPUT_LEN: usize = 32;
+// Backends without a fused copy copy this much at a time, and checksum it while it is in cache.
+const COPY_BLOCK_LEN: usize = 4096;
 
 #[derive(Copy, Clone, Debug)]
 /// Represents an in-progress Adler-32 computation.
 pub struct Adler32 {
-    state: State,
+    state: (u32, u32),
+    kernel: &'static Kernel,
 }
 
 impl Adler32 {
     /// Create a new `Adler32`.
     ///
-    /// This will perform a CPU feature detection at runtime to select the most
-    /// optimal implementation for the current processor architecture.
+    /// The first call in a process performs a CPU feature detection at runtime to select
+    /// the most optimal implementation for the current processor architecture; the result
+    /// is cached for subsequent calls.
     pub fn new() -> Self {
         Self::from(DEFAULT_INIT_STATE)
     }
 
+    /// Create a new `Adler32` using the given implementation.
+    ///
+    /// Returns `None` if the implementation is not supported by the current CPU.
+    pub fn with_backend(backend: Backend, initial: u32) -> Option<Self> {
+        backend
+            .kernel()
+            .map(|kernel| Self::with_kernel(kernel, initial))
+    }
+
     /// Return the computed Adler-32 value.
     pub fn as_u32(&self) -> u32 {
-        match self.state {
-            State::Baseline(state) => state.finalize(),
-            State::Specialized(state) => state.finalize(),
-        }
+        self.state.0 | (self.state.1 << 16)
+    }
+
+    /// Combine the checksum of a following segment into this one.
+    ///
+    /// After this call, `self` holds the checksum of the concatenation of the data
+    /// processed by `self` and the `len2` bytes processed by `other`. `other` must have
+    /// been started from the default initial state.
+    pub fn combine(&mut self, other: &Self, len2: u64) {
+        self.combine_u32(other.as_u32(), len2);
+    }
+
+    fn combine_u32(&mut self, other: u32, len2: u64) {
+        let combined = combine(self.as_u32(), other, len2);
+        self.state = (combined & 0xffff, combined >> 16);
+    }
+
+    /// Return the implementation used by this `Adler32`.
+    pub fn backend(&self) -> Backend {
+        self.kernel.backend
     }
 
     /// Indicates whether the current implementation is SIMD-accelerated.
     pub fn is_simd_enabled(&self) -> bool {
-        match self.state {
-            State::Specialized(_) => true,
-            _ => false,
-        }
+        !matches!(self.kernel.backend, Backend::Baseline | Backend::Swar)
     }
 
     /// Reset the hash state.
     pub fn reset(&mut self) {
-        match self.state {
-            State::Baseline(ref mut state) => state.reset(),
-            State::Specialized(ref mut state) => state.reset(),
-        }
+        self.state = (1, 0);
     }
 
     /// Process the given byte slice and update the hash state.
+    #[inline]
     pub fn update(&mut self, buf: &[u8]) {
-        match self.state {
-            State::Baseline(ref mut state) => state.update(buf),
-            State::Specialized(ref mut state) => state.update(buf),
+        if buf.len() < SHORT_INPUT_LEN {
+            // No backend gets past its tail handling for these, so skip the dispatch entirely.
+            self.state = baseline::update_tail(self.state.0, self.state.1, buf);
+            return;
         }
+        // SAFETY: Kernels are only handed out for backends supported by the CPU.
+        self.state = unsafe { specialized::update(self.kernel, self.state.0, self.state.1, buf) };
     }
 
-    fn internal_new_baseline(initial: u32) -> Self {
+    /// Copy `src` into `dst` and update the hash state with it.
+    ///
+    /// This is equivalent to `dst.copy_from_slice(src)` followed by `update(dst)`, but reads
+    /// `src` only once where the selected implementation supports it.
+    ///
+    /// ```
+    /// use adler32fast::Adler32;
+    ///
+    /// let mut window = [0; 11];
+    /// let mut adler32 = Adler32::new();
+    /// adler32.update_copy(&mut window, b"foo bar baz");
+    ///
+    /// assert_eq!(&window, b"foo bar baz");
+    /// assert_eq!(adler32.as_u32(), 0x17ba03f7);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `dst` and `src` have different lengths.
+    pub fn update_copy(&mut self, dst: &mut [u8], src: &[u8]) {
+        assert_eq!(
+            dst.len(),
+            src.len(),
+            "`dst` and `src` must have the same length"
+        );
+        if src.len() < SHORT_INPUT_LEN {
+            dst.copy_from_slice(src);
+            self.state = baseline::update_tail(self.state.0, self.state.1, dst);
+            return;
+        }
+        match self.kernel.update_copy {
+            Some(update_copy) => {
+                // SAFETY: Kernels are only handed out for backends supported by the CPU.
+                self.state = unsafe { update_copy(self.state.0, self.state.1, dst, src) };
+            }
+            None => {
+                for (dst, src) in dst
+                    .chunks_mut(COPY_BLOCK_LEN)
+                    .zip(src.chunks(COPY_BLOCK_LEN))
+                {
+                    dst.copy_from_slice(src);
+                    self.update(dst);
+                }
+            }
+        }
+    }
+
+    fn with_kernel(kernel: &'static Kernel, initial: u32) -> Self {
         Self {
-            state: State::Baseline(baseline::State::new(initial)),
+            state: (initial & 0xffff, initial >> 16),
+            kernel,
         }
     }
+}
 
-    #[doc(hidden)]
-    fn internal_new_specialized(initial: u32) -> Option<Self> {
-        specialized::State::new(initial).map(|state| Self {
-            state: State::Specialized(state),
-        })
+/// Compute the Adler-32 checksum of two concatenated segments.
+///
+/// Given `adler1`, the checksum of a first segment, and `adler2`, the checksum of a second
+/// segment of `len2` bytes, this returns the checksum of both segments back-to-back without
+/// processing the data again. This is equivalent to zlib's `adler32_combine`.
+///
+/// ```
+/// use adler32fast::Adler32;
+///
+/// let mut a = Adler32::new();
+/// a.update(b"foo ");
+/// let mut b = Adler32::new();
+/// b.update(b"bar baz");
+/// let mut full = Adler32::new();
+/// full.update(b"foo bar baz");
+///
+/// assert_eq!(adler32fast::combine(a.as_u32(), b.as_u32(), 7), full.as_u32());
+/// ```
+pub fn combine(adler1: u32, adler2: u32, len2: u64) -> u32 {
+    let base = baseline::BASE;
+    let rem = (len2 % u64::from(base)) as u32;
+    let mut sum1 = adler1 & 0xffff;
+    let mut sum2 = (rem * sum1) % base;
+    sum1 += (adler2 & 0xffff) + base - 1;
+    sum2 += (adler1 >> 16) + (adler2 >> 16) + base - rem;
+    if sum1 >= base {
+        sum1 -= base;
+    }
+    if sum1 >= base {
+        sum1 -= base;
+    }
+    if sum2 >= base << 1 {
+        sum2 -= base << 1;
     }
+    if sum2 >= base {
+        sum2 -= base;
+    }
+    sum1 | (sum2 << 16)
 }
 
 impl Default for Adler32 {
@@ -107,8 +283,7 @@ impl Default for Adler32 {
 
 impl From<u32> for Adler32 {
     fn from(initial: u32) -> Self {
-        Self::internal_new_specialized(initial)
-            .unwrap_or_else(|| Self::internal_new_baseline(initial))
+        Self::with_kernel(specialized::detect(), initial)
     }
 }
 
@@ -127,3 +302,108 @@ impl PartialEq<u32> for Adler32 {
         self.as_u32() == other
     }
 }
+
+/// The error returned when a stored Adler-32 checksum does not match the computed value.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ChecksumMismatch {
+    /// The checksum stored alongside the data.
+    pub expected: u32,
+    /// The checksum computed from the data.
+    pub actual: u32,
+}
+
+impl fmt::Display for ChecksumMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Adler-32 checksum mismatch: expected {:#010x}, computed {:#010x}",
+            self.expected, self.actual
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ChecksumMismatch {}
+
+#[cfg(test)]
+mod tests {
+    use super::{Adler32, Backend};
+
+    quickcheck::quickcheck! {
+        fn combine_is_same_as_concatenation(init: u32, first: Vec<u8>, second: Vec<u8>) -> bool {
+            let mut expected = Adler32::from(init);
+            expected.update(&first);
+            expected.update(&second);
+
+            let mut actual = Adler32::from(init);
+            actual.update(&first);
+            let mut other = Adler32::new();
+            other.update(&second);
+            actual.combine(&other, second.len() as u64);
+
+            expected.as_u32() == actual.as_u32()
+        }
+
+        fn update_copy_is_same_as_update(init: u32, buf: Vec<u8>) -> bool {
+            let mut expected = Adler32::from(init);
+            expected.update(&buf);
+
+            let mut actual = Adler32::from(init);
+            let mut dst = vec![0; buf.len()];
+            actual.update_copy(&mut dst, &buf);
+
+            expected.as_u32() == actual.as_u32() && dst == buf
+        }
+    }
+
+    #[test]
+    fn update_copy_is_valid_for_every_backend() {
+        let src: Vec<u8> = (0..100_000u32).map(|i| (i * 31 % 251) as u8).collect();
+        for backend in Backend::available() {
+            for &len in &[0, 31, 32, 4095, 4096, 5537, 70_000, 100_000] {
+                for &offset in &[0, 1, 17] {
+                    let src = &src[offset..len.max(offset)];
+                    let mut expected = Adler32::with_backend(backend, 0xfff0_fff0).unwrap();
+                    expected.update(src);
+                    let mut actual = Adler32::with_backend(backend, 0xfff0_fff0).unwrap();
+                    let mut dst = vec![0; src.len() + offset];
+                    actual.update_copy(&mut dst[offset..], src);
+                    assert_eq!(actual.as_u32(), expected.as_u32(), "{} {}", backend, len);
+                    assert_eq!(&dst[offset..], src, "{} {}", backend, len);
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn update_copy_checks_lengths() {
+        Adler32::new().update_copy(&mut [0; 2], b"foo");
+    }
+
+    #[test]
+    fn combine_is_valid_for_large_input() {
+        let first = vec![0xff; 100_000];
+        let second = vec![0xfe; 200_000];
+        let mut expected = Adler32::new();
+        expected.update(&first);
+        expected.update(&second);
+
+        let mut a = Adler32::new();
+        a.update(&first);
+        let mut b = Adler32::new();
+        b.update(&second);
+        assert_eq!(
+            super::combine(a.as_u32(), b.as_u32(), second.len() as u64),
+            expected.as_u32()
+        );
+    }
+
+    #[test]
+    fn combine_with_empty_segment_is_identity() {
+        let mut a = Adler32::new();
+        a.update(b"foo bar baz");
+        assert_eq!(super::combine(a.as_u32(), 1, 0), a.as_u32());
+        assert_eq!(super::combine(1, a.as_u32(), 11), a.as_u32());
+    }
+}
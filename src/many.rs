@@ -0,0 +1,119 @@
+use crate::backend::Kernel;
+use crate::specialized;
+
+/// Compute the Adler-32 checksums of many independent buffers.
+///
+/// This is equivalent to checksumming each buffer with a new `Adler32`. With the AVX2 and AVX-512
+/// implementations, buffers are processed in groups of four, and interleaving the groups hides
+/// the latency of each checksum, which pays off for many small buffers of similar sizes. The
+/// other implementations checksum the buffers one after another.
+///
+/// ```
+/// let checksums = adler32fast::checksum_many(&[b"foo", b"bar baz"]);
+/// assert_eq!(checksums, [0x02820145, 0x0a170293]);
+/// ```
+#[cfg(feature = "std")]
+pub fn checksum_many(bufs: &[&[u8]]) -> Vec<u32> {
+    let mut out = vec![0; bufs.len()];
+    checksum_many_into(bufs, &mut out);
+    out
+}
+
+/// Compute the Adler-32 checksums of many independent buffers into `out`.
+///
+/// This is the allocation-free variant of [`checksum_many`](crate::checksum_many); the checksum
+/// of `bufs[i]` is stored in `out[i]`.
+///
+/// # Panics
+///
+/// Panics if `bufs` and `out` have different lengths.
+pub fn checksum_many_into(bufs: &[&[u8]], out: &mut [u32]) {
+    assert_eq!(
+        bufs.len(),
+        out.len(),
+        "`bufs` and `out` must have the same length"
+    );
+    checksum_many_with(specialized::detect(), bufs, out);
+}
+
+fn checksum_many_with(kernel: &Kernel, bufs: &[&[u8]], out: &mut [u32]) {
+    let mut bufs = bufs;
+    let mut out = out;
+    if let Some(update_x4) = kernel.update_x4 {
+        let groups = bufs.chunks_exact(4);
+        let remainder = groups.remainder();
+        let mut out_groups = out.chunks_exact_mut(4);
+        for (group, out_group) in groups.zip(&mut out_groups) {
+            // SAFETY: Kernels are only handed out for backends supported by the CPU.
+            let states =
+                unsafe { update_x4([(1, 0); 4], [group[0], group[1], group[2], group[3]]) };
+            for (checksum, (a, b)) in out_group.iter_mut().zip(states.iter()) {
+                *checksum = a | (b << 16);
+            }
+        }
+        bufs = remainder;
+        out = out_groups.into_remainder();
+    }
+    for (buf, checksum) in bufs.iter().zip(out.iter_mut()) {
+        // SAFETY: Kernels are only handed out for backends supported by the CPU.
+        let (a, b) = unsafe { specialized::update(kernel, 1, 0, buf) };
+        *checksum = a | (b << 16);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Adler32, Backend};
+
+    fn serial(bufs: &[&[u8]]) -> Vec<u32> {
+        bufs.iter()
+            .map(|buf| {
+                let mut adler32 = Adler32::new();
+                adler32.update(buf);
+                adler32.as_u32()
+            })
+            .collect()
+    }
+
+    quickcheck::quickcheck! {
+        fn checksum_many_is_same_as_serial(bufs: Vec<Vec<u8>>) -> bool {
+            let bufs: Vec<&[u8]> = bufs.iter().map(Vec::as_slice).collect();
+            let mut actual = vec![0; bufs.len()];
+            super::checksum_many_into(&bufs, &mut actual);
+            actual == serial(&bufs)
+        }
+    }
+
+    #[test]
+    fn checksum_many_is_valid_for_every_backend() {
+        // Equal and unequal sizes, spanning several NMAX blocks of every backend.
+        let data: Vec<u8> = (0..70_000u32).map(|i| (i * 31 % 251) as u8).collect();
+        let lens = [4096, 4096, 4096, 4096, 20_000, 70_000, 31, 0, 6000, 12_345];
+        let bufs: Vec<&[u8]> = lens.iter().map(|&len| &data[..len]).collect();
+        let expected = serial(&bufs);
+        for backend in Backend::available() {
+            let mut actual = vec![0; bufs.len()];
+            super::checksum_many_with(backend.kernel().unwrap(), &bufs, &mut actual);
+            assert_eq!(actual, expected, "{}", backend);
+        }
+    }
+
+    #[test]
+    fn checksum_many_is_interleaved_with_avx2() {
+        let detected = crate::specialized::detect();
+        // Only AVX2 and up are selected statically, ahead of any better backend.
+        if !cfg!(target_feature = "avx2") {
+            assert_eq!(Some(detected.backend), Backend::available().next());
+        }
+        // Every backend from AVX2 up processes four buffers at once.
+        if Backend::Avx2.is_available() {
+            assert!(detected.update_x4.is_some());
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn checksum_many_into_checks_output_length() {
+        super::checksum_many_into(&[b"foo"], &mut [0; 2]);
+    }
+}
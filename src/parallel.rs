@@ -0,0 +1,62 @@
+use rayon::prelude::*;
+
+use crate::Adler32;
+
+// Inputs smaller than this are not worth the overhead of spawning work.
+const MIN_CHUNK_SIZE: usize = 256 * 1024;
+
+impl Adler32 {
+    /// Process the given byte slice on the rayon thread pool and update the hash state.
+    ///
+    /// The input is split into one segment per thread, each segment is checksummed with the
+    /// same implementation as `self`, and the partial results are merged with
+    /// [`combine`](crate::combine). The result is identical to calling `update`.
+    pub fn update_parallel(&mut self, buf: &[u8]) {
+        if buf.len() < MIN_CHUNK_SIZE * 2 {
+            self.update(buf);
+            return;
+        }
+        let chunk_size = (buf.len() / rayon::current_num_threads()).max(MIN_CHUNK_SIZE);
+        let mut template = *self;
+        template.reset();
+
+        let (checksum, len) = buf
+            .par_chunks(chunk_size)
+            .map(|chunk| {
+                let mut adler32 = template;
+                adler32.update(chunk);
+                (adler32.as_u32(), chunk.len() as u64)
+            })
+            .reduce(
+                || (1, 0),
+                |(adler1, len1), (adler2, len2)| {
+                    (crate::combine(adler1, adler2, len2), len1 + len2)
+                },
+            );
+        self.combine_u32(checksum, len);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Adler32;
+
+    #[test]
+    fn parallel_is_same_as_serial() {
+        let buf: Vec<u8> = (0..5_000_000u32).map(|i| (i * 7 + i / 13) as u8).collect();
+        for &len in &[
+            0,
+            1,
+            100_000,
+            super::MIN_CHUNK_SIZE * 2,
+            3_333_333,
+            buf.len(),
+        ] {
+            let mut expected = Adler32::from(0x1234_5678);
+            expected.update(&buf[..len]);
+            let mut actual = Adler32::from(0x1234_5678);
+            actual.update_parallel(&buf[..len]);
+            assert_eq!(expected.as_u32(), actual.as_u32(), "len = {}", len);
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use crate::baseline::BASE;
+use crate::Adler32;
+
+/// Represents an Adler-32 checksum over a sliding window of bytes.
+///
+/// The initial window is processed with the fastest available implementation, after which the
+/// window can be advanced one byte at a time in constant time.
+#[derive(Copy, Clone, Debug)]
+pub struct RollingAdler32 {
+    state: (u32, u32),
+    window_len: usize,
+    // `window_len % BASE`, cached so that `roll` only needs a single multiplication.
+    window_len_mod: u32,
+}
+
+impl RollingAdler32 {
+    /// Create a new `RollingAdler32` over the given initial window.
+    pub fn new(window: &[u8]) -> Self {
+        let mut adler32 = Adler32::new();
+        adler32.update(window);
+        let checksum = adler32.as_u32();
+        Self {
+            state: (checksum & 0xffff, checksum >> 16),
+            window_len: window.len(),
+            window_len_mod: (window.len() % BASE as usize) as u32,
+        }
+    }
+
+    /// Return the computed Adler-32 value of the current window.
+    pub fn as_u32(&self) -> u32 {
+        self.state.0 | (self.state.1 << 16)
+    }
+
+    /// Advance the window by one byte, removing `out_byte` from the front of the window and
+    /// appending `in_byte` to the back.
+    ///
+    /// `out_byte` must be the oldest byte in the current window.
+    pub fn roll(&mut self, out_byte: u8, in_byte: u8) {
+        let (a, b) = self.state;
+        let a = (a + BASE - u32::from(out_byte) + u32::from(in_byte)) % BASE;
+        let removed = (self.window_len_mod * u32::from(out_byte)) % BASE;
+        let b = (b + a + 2 * BASE - 1 - removed) % BASE;
+        self.state = (a, b);
+    }
+
+    /// Return the number of bytes in the window.
+    pub fn window_len(&self) -> usize {
+        self.window_len
+    }
+}
+
+impl PartialEq<u32> for RollingAdler32 {
+    fn eq(&self, &other: &u32) -> bool {
+        self.as_u32() == other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RollingAdler32;
+    use crate::Adler32;
+
+    fn check_all_windows(buf: &[u8], window_len: usize) -> bool {
+        if window_len > buf.len() {
+            return true;
+        }
+        let mut rolling = RollingAdler32::new(&buf[..window_len]);
+        for start in 0..=buf.len() - window_len {
+            if start > 0 {
+                rolling.roll(buf[start - 1], buf[start + window_len - 1]);
+            }
+            let mut expected = Adler32::new();
+            expected.update(&buf[start..start + window_len]);
+            if rolling.as_u32() != expected.as_u32() {
+                return false;
+            }
+        }
+        rolling.window_len() == window_len
+    }
+
+    quickcheck::quickcheck! {
+        fn rolling_is_same_as_adler32(buf: Vec<u8>, window_len: usize) -> bool {
+            check_all_windows(&buf, window_len % (buf.len() + 1))
+        }
+    }
+
+    #[test]
+    fn rolling_is_valid_for_large_window() {
+        let buf: Vec<u8> = (0..70_000u32).map(|i| (i % 251) as u8 ^ 0xff).collect();
+        assert!(check_all_windows(&buf[..66_000], 65_600));
+        let mut rolling = RollingAdler32::new(&buf[..65_600]);
+        for i in 65_600..buf.len() {
+            rolling.roll(buf[i - 65_600], buf[i]);
+        }
+        let mut expected = Adler32::new();
+        expected.update(&buf[buf.len() - 65_600..]);
+        assert_eq!(rolling.as_u32(), expected.as_u32());
+    }
+}
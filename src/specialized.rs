@@ -1,75 +1,360 @@
 use cfg_if::cfg_if;
 
+use crate::backend::{Crc32Fn, Kernel};
+use crate::Backend;
+
+#[cfg(feature = "nightly")]
+pub mod portable;
+
+// The portable backend is available on every target, so it lives outside the per-architecture
+// backends below.
+#[cfg(feature = "nightly")]
+pub(crate) static PORTABLE: Kernel = Kernel {
+    backend: Backend::Portable,
+    update: portable::update_simd,
+    update_x4: None,
+    update_copy: None,
+    update_crc32: None,
+};
+
+// The kernel used when no specialized backend is supported. Without SIMD, 64-bit targets are
+// best served by the SWAR implementation. Targets that statically select AVX2 or better (which
+// every AVX-512 target implies) or simd128 never fall back.
+#[cfg(not(any(
+    all(
+        any(target_arch = "x86", target_arch = "x86_64"),
+        target_feature = "avx2"
+    ),
+    all(target_arch = "wasm32", target_feature = "simd128")
+)))]
+fn fallback() -> &'static Kernel {
+    if cfg!(target_pointer_width = "64") {
+        &crate::baseline::swar::KERNEL
+    } else {
+        &crate::baseline::KERNEL
+    }
+}
+
+// CRC-32 is only accelerated with carry-less multiplication, which the `update_crc32` entry
+// points of the kernels also need.
 cfg_if! {
     if #[cfg(any(target_arch = "x86", target_arch = "x86_64"))] {
+        pub(crate) fn detect_crc32() -> Option<Crc32Fn> {
+            if pclmulqdq::is_supported() {
+                Some(pclmulqdq::update_simd)
+            } else {
+                None
+            }
+        }
+    } else {
+        pub(crate) fn detect_crc32() -> Option<Crc32Fn> {
+            None
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(any(target_arch = "x86", target_arch = "x86_64"))] {
+        use core::ptr;
+
         pub mod avx2;
+        mod avx512;
+        pub mod avx512bw;
+        pub mod avx512vnni;
+        #[cfg(any(not(feature = "std"), test))]
+        mod cpuid;
+        pub(crate) mod pclmulqdq;
+        pub mod sse2;
         pub mod ssse3;
 
-        #[derive(Copy, Clone, Debug)]
-        pub enum Arch {
-            Avx2(avx2::State),
-            Ssse3(ssse3::State),
-        }
+        static AVX512VNNI: Kernel = Kernel {
+            backend: Backend::Avx512Vnni,
+            update: avx512vnni::update_simd,
+            update_x4: Some(avx512vnni::update_x4),
+            update_copy: None,
+            update_crc32: None,
+        };
 
-        #[derive(Copy, Clone, Debug)]
-        pub struct State {
-            arch: Arch,
-        }
+        static AVX512BW: Kernel = Kernel {
+            backend: Backend::Avx512Bw,
+            update: avx512bw::update_simd,
+            update_x4: Some(avx512bw::update_x4),
+            update_copy: None,
+            update_crc32: None,
+        };
 
-        impl State {
-            pub fn new(initial: u32) -> Option<Self> {
-                avx2::State::new(initial)
-                    .map(|a| Self {
-                        arch: Arch::Avx2(a),
-                    })
-                    .or_else(|| {
-                        ssse3::State::new(initial).map(|a| Self {
-                            arch: Arch::Ssse3(a),
-                        })
-                    })
+        static AVX2: Kernel = Kernel {
+            backend: Backend::Avx2,
+            update: avx2::update_simd,
+            update_x4: Some(avx2::update_x4),
+            update_copy: Some(avx2::update_copy_simd),
+            update_crc32: Some(avx2::update_crc32_simd),
+        };
+
+        static SSSE3: Kernel = Kernel {
+            backend: Backend::Ssse3,
+            update: ssse3::update_simd,
+            update_x4: None,
+            update_copy: Some(ssse3::update_copy_simd),
+            update_crc32: Some(ssse3::update_crc32_simd),
+        };
+
+        static SSE2: Kernel = Kernel {
+            backend: Backend::Sse2,
+            update: sse2::update_simd,
+            update_x4: None,
+            update_copy: None,
+            update_crc32: None,
+        };
+
+        pub(crate) fn kernel(backend: Backend) -> Option<&'static Kernel> {
+            match backend {
+                Backend::Avx512Vnni if avx512vnni::is_supported() => Some(&AVX512VNNI),
+                Backend::Avx512Bw if avx512bw::is_supported() => Some(&AVX512BW),
+                Backend::Avx2 if avx2::is_supported() => Some(&AVX2),
+                Backend::Ssse3 if ssse3::is_supported() => Some(&SSSE3),
+                Backend::Sse2 if sse2::is_supported() => Some(&SSE2),
+                _ => None,
             }
+        }
+
+        // When AVX2 or AVX-512 is enabled at compile time, the best enabled backend is selected
+        // without any runtime detection and `update` calls it directly so that it can be inlined.
+        // SSSE3 and SSE2 are enabled by common targets such as `x86-64-v2` and `x86_64`, so they
+        // only set the floor of the runtime detection, which still looks for the higher tiers.
+        cfg_if! {
+            if #[cfg(all(
+                target_feature = "avx512f",
+                target_feature = "avx512bw",
+                target_feature = "avx512vnni"
+            ))] {
+                pub(crate) fn detect() -> &'static Kernel {
+                    &AVX512VNNI
+                }
+
+                #[inline(always)]
+                pub(crate) unsafe fn update(
+                    kernel: &Kernel,
+                    a: u32,
+                    b: u32,
+                    buf: &[u8],
+                ) -> (u32, u32) {
+                    if ptr::eq(kernel, &AVX512VNNI) {
+                        avx512vnni::update_simd(a, b, buf)
+                    } else {
+                        (kernel.update)(a, b, buf)
+                    }
+                }
+            } else if #[cfg(all(target_feature = "avx512f", target_feature = "avx512bw"))] {
+                pub(crate) fn detect() -> &'static Kernel {
+                    &AVX512BW
+                }
+
+                #[inline(always)]
+                pub(crate) unsafe fn update(
+                    kernel: &Kernel,
+                    a: u32,
+                    b: u32,
+                    buf: &[u8],
+                ) -> (u32, u32) {
+                    if ptr::eq(kernel, &AVX512BW) {
+                        avx512bw::update_simd(a, b, buf)
+                    } else {
+                        (kernel.update)(a, b, buf)
+                    }
+                }
+            } else if #[cfg(target_feature = "avx2")] {
+                pub(crate) fn detect() -> &'static Kernel {
+                    &AVX2
+                }
+
+                #[inline(always)]
+                pub(crate) unsafe fn update(
+                    kernel: &Kernel,
+                    a: u32,
+                    b: u32,
+                    buf: &[u8],
+                ) -> (u32, u32) {
+                    if ptr::eq(kernel, &AVX2) {
+                        avx2::update_simd(a, b, buf)
+                    } else {
+                        (kernel.update)(a, b, buf)
+                    }
+                }
+            } else {
+                use core::sync::atomic::{AtomicPtr, Ordering};
+
+                // The kernel selected by the first call to `detect`, or null if detection has not
+                // run yet.
+                static DETECTED: AtomicPtr<Kernel> = AtomicPtr::new(ptr::null_mut());
 
-            pub fn finalize(self) -> u32 {
-                match self.arch {
-                    Arch::Avx2(state) => state.finalize(),
-                    Arch::Ssse3(state) => state.finalize(),
+                pub(crate) fn detect() -> &'static Kernel {
+                    let detected = DETECTED.load(Ordering::Relaxed);
+                    if !detected.is_null() {
+                        // SAFETY: `DETECTED` only ever holds pointers to the kernel statics.
+                        return unsafe { &*detected };
+                    }
+                    let kernel = kernel(Backend::Avx512Vnni)
+                        .or_else(|| kernel(Backend::Avx512Bw))
+                        .or_else(|| kernel(Backend::Avx2))
+                        .or_else(|| kernel(Backend::Ssse3))
+                        .or_else(|| kernel(Backend::Sse2))
+                        .or_else(|| Backend::Portable.kernel())
+                        .unwrap_or_else(fallback);
+                    // Racing threads compute the same result, so a plain store is sufficient.
+                    DETECTED.store(kernel as *const Kernel as *mut Kernel, Ordering::Relaxed);
+                    kernel
+                }
+
+                #[inline(always)]
+                pub(crate) unsafe fn update(
+                    kernel: &Kernel,
+                    a: u32,
+                    b: u32,
+                    buf: &[u8],
+                ) -> (u32, u32) {
+                    #[cfg(target_feature = "ssse3")]
+                    if ptr::eq(kernel, &SSSE3) {
+                        return ssse3::update_simd(a, b, buf);
+                    }
+                    (kernel.update)(a, b, buf)
                 }
             }
+        }
+    } else if #[cfg(target_arch = "aarch64")] {
+        use core::ptr;
+
+        pub mod neon;
 
-            pub fn reset(&mut self) {
-                match self.arch {
-                    Arch::Avx2(ref mut state) => state.reset(),
-                    Arch::Ssse3(ref mut state) => state.reset(),
-                };
+        static NEON: Kernel = Kernel {
+            backend: Backend::Neon,
+            update: neon::update_simd,
+            update_x4: None,
+            update_copy: None,
+            update_crc32: None,
+        };
+
+        pub(crate) fn kernel(backend: Backend) -> Option<&'static Kernel> {
+            match backend {
+                Backend::Neon if neon::is_supported() => Some(&NEON),
+                _ => None,
             }
+        }
 
-            pub fn update(&mut self, buf: &[u8]) {
-                match self.arch {
-                    Arch::Avx2(ref mut state) => state.update(buf),
-                    Arch::Ssse3(ref mut state) => state.update(buf),
-                }
+        // NEON is part of every standard AArch64 target, so detection there is a compile-time
+        // constant and needs no cache.
+        pub(crate) fn detect() -> &'static Kernel {
+            kernel(Backend::Neon)
+                .or_else(|| Backend::Portable.kernel())
+                .unwrap_or_else(fallback)
+        }
+
+        #[inline(always)]
+        pub(crate) unsafe fn update(
+            kernel: &Kernel,
+            a: u32,
+            b: u32,
+            buf: &[u8],
+        ) -> (u32, u32) {
+            #[cfg(target_feature = "neon")]
+            if ptr::eq(kernel, &NEON) {
+                return neon::update_simd(a, b, buf);
             }
+            (kernel.update)(a, b, buf)
         }
-    } else {
-        #[derive(Copy, Clone, Debug)]
-        pub enum State {}
+    } else if #[cfg(all(target_arch = "riscv64", feature = "nightly"))] {
+        pub mod rvv;
 
-        impl State {
-            pub fn new(_: u32) -> Option<Self> {
-                None
+        static RVV: Kernel = Kernel {
+            backend: Backend::Rvv,
+            update: rvv::update_simd,
+            update_x4: None,
+            update_copy: None,
+            update_crc32: None,
+        };
+
+        pub(crate) fn kernel(backend: Backend) -> Option<&'static Kernel> {
+            match backend {
+                Backend::Rvv if rvv::is_supported() => Some(&RVV),
+                _ => None,
             }
+        }
+
+        // The standard library caches its own detection of the V extension.
+        pub(crate) fn detect() -> &'static Kernel {
+            kernel(Backend::Rvv)
+                .or_else(|| Backend::Portable.kernel())
+                .unwrap_or_else(fallback)
+        }
 
-            pub fn finalize(self) -> u32 {
-                unimplemented!()
+        #[inline(always)]
+        pub(crate) unsafe fn update(
+            kernel: &Kernel,
+            a: u32,
+            b: u32,
+            buf: &[u8],
+        ) -> (u32, u32) {
+            #[cfg(target_feature = "v")]
+            if core::ptr::eq(kernel, &RVV) {
+                return rvv::update_simd(a, b, buf);
             }
+            (kernel.update)(a, b, buf)
+        }
+    } else if #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))] {
+        use core::ptr;
 
-            pub fn reset(&mut self) {
-                unimplemented!()
+        pub mod simd128;
+
+        static SIMD128: Kernel = Kernel {
+            backend: Backend::Simd128,
+            update: simd128::update_simd,
+            update_x4: None,
+            update_copy: None,
+            update_crc32: None,
+        };
+
+        pub(crate) fn kernel(backend: Backend) -> Option<&'static Kernel> {
+            match backend {
+                Backend::Simd128 => Some(&SIMD128),
+                _ => None,
             }
+        }
 
-            pub fn update(&mut self, _: &[u8]) {
-                unimplemented!()
+        pub(crate) fn detect() -> &'static Kernel {
+            &SIMD128
+        }
+
+        #[inline(always)]
+        pub(crate) unsafe fn update(
+            kernel: &Kernel,
+            a: u32,
+            b: u32,
+            buf: &[u8],
+        ) -> (u32, u32) {
+            if ptr::eq(kernel, &SIMD128) {
+                simd128::update_simd(a, b, buf)
+            } else {
+                (kernel.update)(a, b, buf)
             }
         }
+    } else {
+        pub(crate) fn kernel(_: Backend) -> Option<&'static Kernel> {
+            None
+        }
+
+        // Without hand-written intrinsics, the portable backend is preferred when it is built.
+        pub(crate) fn detect() -> &'static Kernel {
+            Backend::Portable.kernel().unwrap_or_else(fallback)
+        }
+
+        #[inline(always)]
+        pub(crate) unsafe fn update(
+            kernel: &Kernel,
+            a: u32,
+            b: u32,
+            buf: &[u8],
+        ) -> (u32, u32) {
+            (kernel.update)(a, b, buf)
+        }
     }
 }
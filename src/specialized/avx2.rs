@@ -1,5 +1,11 @@
-const NMAX: usize = 5536;
+use super::pclmulqdq::{self, Folder, FOLD_SIZE};
+
+// The largest multiple of `GROUP_SIZE * CHUNK_SIZE` for which `b` cannot overflow, which is
+// 255 * n * (n + 1) / 2 + (n + 1) * (BASE - 1) <= 2^32 - 1, or n <= 5552.
+const NMAX: usize = 5504;
 const CHUNK_SIZE: usize = 32;
+// The number of blocks processed per iteration of the main loop.
+const GROUP_SIZE: usize = 4;
 
 #[derive(Copy, Clone, Debug)]
 pub struct State {
@@ -7,21 +13,8 @@ pub struct State {
 }
 
 impl State {
-    #[cfg(not(feature = "std"))]
     pub fn new(initial: u32) -> Option<Self> {
-        if cfg!(target_feature = "avx2") {
-            // SAFETY: Ensure that all required instructions are supported by the CPU.
-            Some(Self {
-                state: (initial & 0xffff, initial >> 16),
-            })
-        } else {
-            None
-        }
-    }
-
-    #[cfg(feature = "std")]
-    pub fn new(initial: u32) -> Option<Self> {
-        if is_x86_feature_detected!("avx2") {
+        if is_supported() {
             // SAFETY: Ensure that all required instructions are supported by the CPU.
             Some(Self {
                 state: (initial & 0xffff, initial >> 16),
@@ -44,21 +37,98 @@ impl State {
     }
 }
 
+#[cfg(target_feature = "avx2")]
+pub(crate) fn is_supported() -> bool {
+    true
+}
+
+#[cfg(all(not(target_feature = "avx2"), not(feature = "std")))]
+pub(crate) fn is_supported() -> bool {
+    super::cpuid::has_avx2()
+}
+
+#[cfg(all(not(target_feature = "avx2"), feature = "std"))]
+pub(crate) fn is_supported() -> bool {
+    is_x86_feature_detected!("avx2")
+}
+
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn update_simd(mut a: u32, mut b: u32, buf: &[u8]) -> (u32, u32) {
+    let chunks = buf.chunks_exact(NMAX);
+    let mut remainder = chunks.remainder();
+    for chunk in chunks {
+        add_reduce(&mut a, &mut b, chunk, None, None);
+        a = crate::baseline::reduce(a);
+        b = crate::baseline::reduce(b);
+    }
+    remainder = add_reduce(&mut a, &mut b, remainder, None, None);
+    update_tail(a, b, buf, remainder.len())
+}
+
 #[target_feature(enable = "avx2")]
-unsafe fn update_simd(mut a: u32, mut b: u32, buf: &[u8]) -> (u32, u32) {
+pub(crate) unsafe fn update_copy_simd(
+    mut a: u32,
+    mut b: u32,
+    dst: &mut [u8],
+    src: &[u8],
+) -> (u32, u32) {
+    let chunks = src.chunks_exact(NMAX);
+    let mut remainder = chunks.remainder();
+    let mut dst_chunks = dst.chunks_exact_mut(NMAX);
+    for (chunk, dst_chunk) in chunks.zip(&mut dst_chunks) {
+        add_reduce(&mut a, &mut b, chunk, Some(dst_chunk), None);
+        a = crate::baseline::reduce(a);
+        b = crate::baseline::reduce(b);
+    }
+    let dst = dst_chunks.into_remainder();
+    remainder = add_reduce(&mut a, &mut b, remainder, Some(&mut *dst), None);
+    let copied = dst.len() - remainder.len();
+    dst[copied..].copy_from_slice(remainder);
+    update_tail(a, b, src, remainder.len())
+}
+
+#[target_feature(enable = "avx2,pclmulqdq")]
+pub(crate) unsafe fn update_crc32_simd(
+    mut a: u32,
+    mut b: u32,
+    crc: u32,
+    buf: &[u8],
+) -> (u32, u32, u32) {
+    const GROUP_LEN: usize = GROUP_SIZE * CHUNK_SIZE;
+    if buf.len() < FOLD_SIZE + GROUP_LEN {
+        let (a, b) = update_simd(a, b, buf);
+        return (a, b, pclmulqdq::update_simd(crc, buf));
+    }
+    // The CRC-32 starts from its first bytes, so the groups of the main loop follow them.
+    let (head, buf) = buf.split_at(FOLD_SIZE);
+    let mut folder = Folder::new(crc, head);
+    add_reduce(&mut a, &mut b, head, None, None);
+    a = crate::baseline::reduce(a);
+    b = crate::baseline::reduce(b);
     let chunks = buf.chunks_exact(NMAX);
     let mut remainder = chunks.remainder();
     for chunk in chunks {
-        add_reduce(&mut a, &mut b, chunk);
-        a %= crate::baseline::BASE;
-        b %= crate::baseline::BASE;
+        add_reduce(&mut a, &mut b, chunk, None, Some(&mut folder));
+        a = crate::baseline::reduce(a);
+        b = crate::baseline::reduce(b);
     }
-    remainder = add_reduce(&mut a, &mut b, remainder);
-    crate::baseline::update_slow(a, b, remainder)
+    // Only whole groups are folded, so the CRC-32 finishes with the blocks after them.
+    let unfolded = &remainder[remainder.len() / GROUP_LEN * GROUP_LEN..];
+    remainder = add_reduce(&mut a, &mut b, remainder, None, Some(&mut folder));
+    let (a, b) = update_tail(a, b, buf, remainder.len());
+    (a, b, folder.finish(unfolded))
 }
 
+// When `dst` is given, each block is also stored to the same offset in it, so that copying the
+// input costs no extra loads. When `crc` is given, each group of blocks is also folded into it.
 #[inline(always)]
-unsafe fn add_reduce<'a>(a: &mut u32, b: &mut u32, chunk: &'a [u8]) -> &'a [u8] {
+unsafe fn add_reduce<'a>(
+    a: &mut u32,
+    b: &mut u32,
+    chunk: &'a [u8],
+    mut dst: Option<&mut [u8]>,
+    mut crc: Option<&mut Folder>,
+) -> &'a [u8] {
     #[cfg(target_arch = "x86")]
     use core::arch::x86::*;
     #[cfg(target_arch = "x86_64")]
@@ -74,14 +144,61 @@ unsafe fn add_reduce<'a>(a: &mut u32, b: &mut u32, chunk: &'a [u8]) -> &'a [u8]
         26, 27, 28, 29, 30, 31, 32,
     );
 
-    let inner_chunks = chunk.chunks_exact(CHUNK_SIZE);
+    let groups = chunk.chunks_exact(GROUP_SIZE * CHUNK_SIZE);
+    let inner_chunks = groups.remainder().chunks_exact(CHUNK_SIZE);
     let remainder = inner_chunks.remainder();
-    let mut p_v = _mm256_set_epi32(0, 0, 0, 0, 0, 0, 0, (*a * inner_chunks.len() as u32) as _);
+    let num_blocks = groups.len() * GROUP_SIZE + inner_chunks.len();
+    let mut p_v = _mm256_set_epi32(0, 0, 0, 0, 0, 0, 0, (*a * num_blocks as u32) as _);
     let mut a_v = _mm256_setzero_si256();
     let mut b_v = _mm256_set_epi32(0, 0, 0, 0, 0, 0, 0, *b as _);
 
-    for inner_chunk in inner_chunks {
+    // Each group of blocks only extends the `a_v`/`p_v` dependency chains once, and the weighted
+    // sums of two blocks can share a `madd` because they cannot exceed 2 * 16065 in 16 bits.
+    let groups_len = groups.len() * GROUP_SIZE * CHUNK_SIZE;
+    for (i, group) in groups.enumerate() {
+        let block0 = _mm256_lddqu_si256(group.as_ptr() as *const __m256i);
+        let block1 = _mm256_lddqu_si256(group[32..].as_ptr() as *const __m256i);
+        let block2 = _mm256_lddqu_si256(group[64..].as_ptr() as *const __m256i);
+        let block3 = _mm256_lddqu_si256(group[96..].as_ptr() as *const __m256i);
+        if let Some(dst) = dst.as_deref_mut() {
+            let dst = dst[i * GROUP_SIZE * CHUNK_SIZE..][..GROUP_SIZE * CHUNK_SIZE].as_mut_ptr();
+            _mm256_storeu_si256(dst as *mut __m256i, block0);
+            _mm256_storeu_si256(dst.add(32) as *mut __m256i, block1);
+            _mm256_storeu_si256(dst.add(64) as *mut __m256i, block2);
+            _mm256_storeu_si256(dst.add(96) as *mut __m256i, block3);
+        }
+        if let Some(crc) = crc.as_deref_mut() {
+            // The upper halves of the blocks would need `vextracti128`, which competes with
+            // `pclmulqdq` and `psadbw` for the same port. Reloading the group from L1 is cheaper.
+            crc.fold(group);
+            crc.fold(&group[FOLD_SIZE..]);
+        }
+        let sum0 = _mm256_sad_epu8(block0, v_zeroes);
+        let sum01 = _mm256_add_epi32(sum0, _mm256_sad_epu8(block1, v_zeroes));
+        let sum012 = _mm256_add_epi32(sum01, _mm256_sad_epu8(block2, v_zeroes));
+        let sum0123 = _mm256_add_epi32(sum012, _mm256_sad_epu8(block3, v_zeroes));
+        // The blocks of the group are preceded by `a_v` and the sums of the blocks before them.
+        p_v = _mm256_add_epi32(p_v, _mm256_slli_epi32(a_v, 2));
+        p_v = _mm256_add_epi32(p_v, _mm256_add_epi32(sum0, _mm256_add_epi32(sum01, sum012)));
+        a_v = _mm256_add_epi32(a_v, sum0123);
+        let mad01 = _mm256_add_epi16(
+            _mm256_maddubs_epi16(block0, v_weights),
+            _mm256_maddubs_epi16(block1, v_weights),
+        );
+        let mad23 = _mm256_add_epi16(
+            _mm256_maddubs_epi16(block2, v_weights),
+            _mm256_maddubs_epi16(block3, v_weights),
+        );
+        b_v = _mm256_add_epi32(b_v, _mm256_madd_epi16(mad01, v_ones));
+        b_v = _mm256_add_epi32(b_v, _mm256_madd_epi16(mad23, v_ones));
+    }
+
+    for (i, inner_chunk) in inner_chunks.enumerate() {
         let block = _mm256_lddqu_si256(inner_chunk.as_ptr() as *const __m256i);
+        if let Some(dst) = dst.as_deref_mut() {
+            let dst = dst[groups_len + i * CHUNK_SIZE..][..CHUNK_SIZE].as_mut_ptr();
+            _mm256_storeu_si256(dst as *mut __m256i, block);
+        }
         p_v = _mm256_add_epi32(p_v, a_v);
         a_v = _mm256_add_epi32(a_v, _mm256_sad_epu8(block, v_zeroes));
         let mad = _mm256_maddubs_epi16(block, v_weights);
@@ -112,6 +229,148 @@ unsafe fn add_reduce<'a>(a: &mut u32, b: &mut u32, chunk: &'a [u8]) -> &'a [u8]
     remainder
 }
 
+// Process the last `len` bytes of `buf`, fewer than `CHUNK_SIZE`, and reduce the sums. The block
+// is loaded so that it ends at the end of `buf`, with the bytes before the tail masked out, so the
+// weights of the tail still count down to 1. Inputs shorter than a block use the scalar tail.
+#[inline(always)]
+unsafe fn update_tail(mut a: u32, mut b: u32, buf: &[u8], len: usize) -> (u32, u32) {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    if buf.len() < CHUNK_SIZE || len == 0 {
+        return crate::baseline::update_tail(a, b, &buf[buf.len() - len..]);
+    }
+    let v_zeroes = _mm256_setzero_si256();
+    let v_ones = _mm256_set1_epi16(1);
+    let v_weights = _mm256_set_epi8(
+        1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
+        26, 27, 28, 29, 30, 31, 32,
+    );
+
+    let block = _mm256_lddqu_si256(buf[buf.len() - CHUNK_SIZE..].as_ptr() as *const __m256i);
+    let v_mask = _mm256_cmpgt_epi8(_mm256_set1_epi8(len as i8 + 1), v_weights);
+    let block = _mm256_and_si256(block, v_mask);
+    let a_v = _mm256_sad_epu8(block, v_zeroes);
+    let b_v = _mm256_madd_epi16(_mm256_maddubs_epi16(block, v_weights), v_ones);
+
+    let mut sum = _mm_add_epi32(
+        _mm256_castsi256_si128(a_v),
+        _mm256_extracti128_si256(a_v, 1),
+    );
+    sum = _mm_add_epi32(sum, _mm_unpackhi_epi64(sum, sum));
+    b += a * len as u32;
+    a += _mm_cvtsi128_si32(sum) as u32;
+
+    sum = _mm_add_epi32(
+        _mm256_castsi256_si128(b_v),
+        _mm256_extracti128_si256(b_v, 1),
+    );
+    sum = _mm_add_epi32(sum, _mm_unpackhi_epi64(sum, sum));
+    sum = _mm_add_epi32(sum, _mm_shuffle_epi32(sum, 177));
+    b += _mm_cvtsi128_si32(sum) as u32;
+
+    (crate::baseline::reduce(a), crate::baseline::reduce(b))
+}
+
+#[target_feature(enable = "avx2")]
+pub(crate) unsafe fn update_x4(mut states: [(u32, u32); 4], bufs: [&[u8]; 4]) -> [(u32, u32); 4] {
+    // The common prefix of the buffers is processed in lockstep, and the rest one at a time.
+    let len = bufs.iter().map(|buf| buf.len()).min().unwrap_or(0);
+    let mut offset = 0;
+    while len - offset >= CHUNK_SIZE {
+        let chunk_len = (len - offset).min(NMAX) & !(CHUNK_SIZE - 1);
+        add_reduce_x4(&mut states, bufs, offset, chunk_len);
+        for (a, b) in &mut states {
+            *a = crate::baseline::reduce(*a);
+            *b = crate::baseline::reduce(*b);
+        }
+        offset += chunk_len;
+    }
+    for ((a, b), buf) in states.iter_mut().zip(bufs.iter()) {
+        if buf.len() > offset {
+            (*a, *b) = update_simd(*a, *b, &buf[offset..]);
+        }
+    }
+    states
+}
+
+// Process `len` bytes, a multiple of `CHUNK_SIZE`, of each buffer starting at `offset`. Each
+// stream has its own weighted sums, while the byte sums of two streams share a register: `sad`
+// leaves the upper half of every 64-bit lane free, and neither half can overflow within `NMAX`.
+// This keeps the four dependency chains within the 16 available registers.
+#[inline(always)]
+unsafe fn add_reduce_x4(states: &mut [(u32, u32); 4], bufs: [&[u8]; 4], offset: usize, len: usize) {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    let v_zeroes = _mm256_setzero_si256();
+    let v_ones = _mm256_set1_epi16(1);
+    let v_weights = _mm256_set_epi8(
+        1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
+        26, 27, 28, 29, 30, 31, 32,
+    );
+
+    let num_blocks = len / CHUNK_SIZE;
+    let ptrs = bufs.map(|buf| buf[offset..offset + len].as_ptr());
+    let mut p_v = [v_zeroes; 2];
+    let mut a_v = [v_zeroes; 2];
+    let mut b_v = [v_zeroes; 4];
+    for pair in 0..2 {
+        let p_lo = states[2 * pair].0 * num_blocks as u32;
+        let p_hi = states[2 * pair + 1].0 * num_blocks as u32;
+        p_v[pair] = _mm256_set_epi32(0, 0, 0, 0, 0, 0, p_hi as _, p_lo as _);
+    }
+
+    for block in 0..num_blocks {
+        let v = ptrs.map(|ptr| _mm256_lddqu_si256(ptr.add(block * CHUNK_SIZE) as *const __m256i));
+        for pair in 0..2 {
+            let v_sum = _mm256_add_epi64(
+                _mm256_sad_epu8(v[2 * pair], v_zeroes),
+                _mm256_slli_epi64(_mm256_sad_epu8(v[2 * pair + 1], v_zeroes), 32),
+            );
+            p_v[pair] = _mm256_add_epi64(p_v[pair], a_v[pair]);
+            a_v[pair] = _mm256_add_epi64(a_v[pair], v_sum);
+        }
+        for i in 0..4 {
+            let mad = _mm256_maddubs_epi16(v[i], v_weights);
+            b_v[i] = _mm256_add_epi32(b_v[i], _mm256_madd_epi16(mad, v_ones));
+        }
+    }
+
+    for pair in 0..2 {
+        let mut sum = _mm_add_epi64(
+            _mm256_castsi256_si128(a_v[pair]),
+            _mm256_extracti128_si256(a_v[pair], 1),
+        );
+        sum = _mm_add_epi64(sum, _mm_unpackhi_epi64(sum, sum));
+        states[2 * pair].0 += _mm_cvtsi128_si32(sum) as u32;
+        states[2 * pair + 1].0 += _mm_extract_epi32(sum, 1) as u32;
+
+        sum = _mm_add_epi64(
+            _mm256_castsi256_si128(p_v[pair]),
+            _mm256_extracti128_si256(p_v[pair], 1),
+        );
+        sum = _mm_add_epi64(sum, _mm_unpackhi_epi64(sum, sum));
+        states[2 * pair].1 += (_mm_cvtsi128_si32(sum) as u32) << 5;
+        states[2 * pair + 1].1 += (_mm_extract_epi32(sum, 1) as u32) << 5;
+    }
+    for i in 0..4 {
+        let mut sum = _mm_add_epi32(
+            _mm256_castsi256_si128(b_v[i]),
+            _mm256_extracti128_si256(b_v[i], 1),
+        );
+        let mut hi = _mm_unpackhi_epi64(sum, sum);
+        sum = _mm_add_epi32(hi, sum);
+        hi = _mm_shuffle_epi32(sum, 177);
+        sum = _mm_add_epi32(sum, hi);
+        states[i].1 += _mm_cvtsi128_si32(sum) as u32;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     quickcheck::quickcheck! {
@@ -139,6 +398,28 @@ mod tests {
             }
             expected.hash() == actual.finalize()
         }
+
+        fn avx2_copy_is_same_as_baseline(init: u32, buf: Vec<u8>) -> bool {
+            let mut expected = crate::baseline::State::new(init);
+            expected.update(&buf);
+            assert!(super::is_supported(), "avx2 not supported");
+            let mut dst = vec![0; buf.len()];
+            let (a, b) =
+                unsafe { super::update_copy_simd(init & 0xffff, init >> 16, &mut dst, &buf) };
+            expected.finalize() == a | (b << 16) && dst == buf
+        }
+
+        fn avx2_crc32_is_same_as_reference(init: u32, crc: u32, buf: Vec<u8>) -> bool {
+            let mut expected = crate::baseline::State::new(init);
+            expected.update(&buf);
+            let mut expected_crc = crc32fast::Hasher::new_with_initial(crc);
+            expected_crc.update(&buf);
+            assert!(super::is_supported(), "avx2 not supported");
+            assert!(super::pclmulqdq::is_supported(), "pclmulqdq not supported");
+            let (a, b, actual_crc) =
+                unsafe { super::update_crc32_simd(init & 0xffff, init >> 16, crc, &buf) };
+            expected.finalize() == a | (b << 16) && expected_crc.finalize() == actual_crc
+        }
     }
 
     #[test]
@@ -150,4 +431,42 @@ mod tests {
         actual.update(&v);
         assert_eq!(expected.finalize(), actual.finalize())
     }
+
+    #[test]
+    fn avx2_is_valid_for_every_tail_length() {
+        // The tails follow whole blocks, so they are loaded together with bytes to mask out.
+        let v: Vec<u8> = (0..4 * super::CHUNK_SIZE)
+            .map(|i| (i * 37) as u8 | 0x80)
+            .collect();
+        for len in 0..=v.len() {
+            let mut expected = crate::baseline::State::new(0xfff0_fff0);
+            let mut actual = super::State::new(0xfff0_fff0).expect("avx2 not supported");
+            expected.update(&v[..len]);
+            actual.update(&v[..len]);
+            assert_eq!(expected.finalize(), actual.finalize(), "length {}", len);
+        }
+    }
+
+    #[test]
+    fn avx2_is_valid_for_worst_case_input() {
+        let v = vec![0xff; super::NMAX * 4 + 127];
+        let mut expected = crate::baseline::State::new(0xfff0_fff0);
+        let mut actual = super::State::new(0xfff0_fff0).expect("avx2 not supported");
+        expected.update(&v);
+        actual.update(&v);
+        assert_eq!(expected.finalize(), actual.finalize())
+    }
+
+    #[test]
+    fn avx2_x4_is_valid_for_worst_case_input() {
+        assert!(super::is_supported(), "avx2 not supported");
+        let v = vec![0xff; super::NMAX * 4 + 127];
+        let bufs = [&v[..], &v[1..], &v[..super::NMAX + 33], &v[5..]];
+        let actual = unsafe { super::update_x4([(0xfff0, 0xfff0); 4], bufs) };
+        for (buf, state) in bufs.iter().zip(actual.iter()) {
+            let mut expected = crate::baseline::State::new(0xfff0_fff0);
+            expected.update(buf);
+            assert_eq!(expected.finalize(), state.0 | (state.1 << 16));
+        }
+    }
 }
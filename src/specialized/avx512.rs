@@ -0,0 +1,156 @@
+// The block loop shared by the AVX-512 backends, which only differ in how they compute the
+// weighted byte sums of each block.
+
+#[cfg(target_arch = "x86")]
+use core::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+
+// The largest multiple of CHUNK_SIZE for which the sums cannot overflow 32 bits.
+pub(crate) const NMAX: usize = 5504;
+pub(crate) const CHUNK_SIZE: usize = 64;
+
+pub(crate) trait WeightedSum {
+    // Add the bytes of `block`, weighted by `v_weights`, to the 32-bit lanes of `b_v`.
+    unsafe fn add(b_v: __m512i, block: __m512i, v_weights: __m512i) -> __m512i;
+}
+
+// The callers enable the target features of `W` as well as AVX-512F and AVX-512BW.
+#[inline(always)]
+pub(crate) unsafe fn update<W: WeightedSum>(mut a: u32, mut b: u32, buf: &[u8]) -> (u32, u32) {
+    let chunks = buf.chunks_exact(NMAX);
+    let mut remainder = chunks.remainder();
+    for chunk in chunks {
+        add_reduce::<W>(&mut a, &mut b, chunk);
+        a = crate::baseline::reduce(a);
+        b = crate::baseline::reduce(b);
+    }
+    remainder = add_reduce::<W>(&mut a, &mut b, remainder);
+    update_tail::<W>(a, b, buf, remainder.len())
+}
+
+#[inline(always)]
+unsafe fn weights() -> __m512i {
+    _mm512_set_epi8(
+        1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25,
+        26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47, 48,
+        49, 50, 51, 52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64,
+    )
+}
+
+#[inline(always)]
+unsafe fn add_reduce<'a, W: WeightedSum>(a: &mut u32, b: &mut u32, chunk: &'a [u8]) -> &'a [u8] {
+    if chunk.len() < CHUNK_SIZE {
+        return chunk;
+    }
+    let v_zeroes = _mm512_setzero_si512();
+    let v_weights = weights();
+
+    let inner_chunks = chunk.chunks_exact(CHUNK_SIZE);
+    let remainder = inner_chunks.remainder();
+    let mut p_v = _mm512_maskz_set1_epi32(1, (*a * inner_chunks.len() as u32) as _);
+    let mut a_v = _mm512_setzero_si512();
+    let mut b_v = _mm512_maskz_set1_epi32(1, *b as _);
+
+    for inner_chunk in inner_chunks {
+        let block = _mm512_loadu_si512(inner_chunk.as_ptr() as *const _);
+        p_v = _mm512_add_epi32(p_v, a_v);
+        a_v = _mm512_add_epi32(a_v, _mm512_sad_epu8(block, v_zeroes));
+        b_v = W::add(b_v, block, v_weights);
+    }
+
+    *a += _mm512_reduce_add_epi32(a_v) as u32;
+    b_v = _mm512_add_epi32(b_v, _mm512_slli_epi32(p_v, 6));
+    *b = _mm512_reduce_add_epi32(b_v) as u32;
+
+    remainder
+}
+
+// Process the last `len` bytes of `buf`, fewer than `CHUNK_SIZE`, and reduce the sums. The block
+// is loaded with a mask so that it ends at the end of `buf`, so the weights of the tail still count
+// down to 1. The bytes before the tail are not read, so this also covers inputs shorter than a
+// block.
+#[inline(always)]
+unsafe fn update_tail<W: WeightedSum>(
+    mut a: u32,
+    mut b: u32,
+    buf: &[u8],
+    len: usize,
+) -> (u32, u32) {
+    if len == 0 {
+        return (crate::baseline::reduce(a), crate::baseline::reduce(b));
+    }
+    let v_zeroes = _mm512_setzero_si512();
+
+    let end = buf.as_ptr().add(buf.len());
+    let block = _mm512_maskz_loadu_epi8(
+        u64::MAX << (CHUNK_SIZE - len),
+        end.wrapping_sub(CHUNK_SIZE) as *const i8,
+    );
+    let a_v = _mm512_sad_epu8(block, v_zeroes);
+    let b_v = W::add(v_zeroes, block, weights());
+
+    b += a * len as u32 + _mm512_reduce_add_epi32(b_v) as u32;
+    a += _mm512_reduce_add_epi32(a_v) as u32;
+
+    (crate::baseline::reduce(a), crate::baseline::reduce(b))
+}
+
+#[inline(always)]
+pub(crate) unsafe fn update_x4<W: WeightedSum>(
+    mut states: [(u32, u32); 4],
+    bufs: [&[u8]; 4],
+) -> [(u32, u32); 4] {
+    // The common prefix of the buffers is processed in lockstep, and the rest one at a time.
+    let len = bufs.iter().map(|buf| buf.len()).min().unwrap_or(0);
+    let mut offset = 0;
+    while len - offset >= CHUNK_SIZE {
+        let chunk_len = (len - offset).min(NMAX) & !(CHUNK_SIZE - 1);
+        add_reduce_x4::<W>(&mut states, bufs, offset, chunk_len);
+        for (a, b) in &mut states {
+            *a = crate::baseline::reduce(*a);
+            *b = crate::baseline::reduce(*b);
+        }
+        offset += chunk_len;
+    }
+    for ((a, b), buf) in states.iter_mut().zip(bufs.iter()) {
+        if buf.len() > offset {
+            (*a, *b) = update::<W>(*a, *b, &buf[offset..]);
+        }
+    }
+    states
+}
+
+// Process `len` bytes, a multiple of `CHUNK_SIZE`, of each buffer starting at `offset`. Each
+// stream keeps its own sums, so that the four dependency chains overlap.
+#[inline(always)]
+unsafe fn add_reduce_x4<W: WeightedSum>(
+    states: &mut [(u32, u32); 4],
+    bufs: [&[u8]; 4],
+    offset: usize,
+    len: usize,
+) {
+    let v_zeroes = _mm512_setzero_si512();
+    let v_weights = weights();
+
+    let num_blocks = len / CHUNK_SIZE;
+    let ptrs = bufs.map(|buf| buf[offset..offset + len].as_ptr());
+    let mut p_v = states.map(|(a, _)| _mm512_maskz_set1_epi32(1, (a * num_blocks as u32) as _));
+    let mut a_v = [v_zeroes; 4];
+    let mut b_v = states.map(|(_, b)| _mm512_maskz_set1_epi32(1, b as _));
+
+    for block in 0..num_blocks {
+        let v = ptrs.map(|ptr| _mm512_loadu_si512(ptr.add(block * CHUNK_SIZE) as *const _));
+        for i in 0..4 {
+            p_v[i] = _mm512_add_epi32(p_v[i], a_v[i]);
+            a_v[i] = _mm512_add_epi32(a_v[i], _mm512_sad_epu8(v[i], v_zeroes));
+            b_v[i] = W::add(b_v[i], v[i], v_weights);
+        }
+    }
+
+    for i in 0..4 {
+        states[i].0 += _mm512_reduce_add_epi32(a_v[i]) as u32;
+        b_v[i] = _mm512_add_epi32(b_v[i], _mm512_slli_epi32(p_v[i], 6));
+        states[i].1 = _mm512_reduce_add_epi32(b_v[i]) as u32;
+    }
+}
@@ -0,0 +1,175 @@
+// Like `avx512bw`, but computes the weighted byte sums with a single `vpdpbusd`.
+
+#[cfg(target_arch = "x86")]
+use core::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+
+use super::avx512::{self, WeightedSum};
+
+#[derive(Copy, Clone, Debug)]
+pub struct State {
+    state: (u32, u32),
+}
+
+impl State {
+    pub fn new(initial: u32) -> Option<Self> {
+        if is_supported() {
+            // SAFETY: Ensure that all required instructions are supported by the CPU.
+            Some(Self {
+                state: (initial & 0xffff, initial >> 16),
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn finalize(self) -> u32 {
+        self.state.0 | (self.state.1 << 16)
+    }
+
+    pub fn reset(&mut self) {
+        self.state = (1, 0)
+    }
+
+    pub fn update(&mut self, buf: &[u8]) {
+        self.state = unsafe { update_simd(self.state.0, self.state.1, buf) }
+    }
+}
+
+#[cfg(all(
+    target_feature = "avx512f",
+    target_feature = "avx512bw",
+    target_feature = "avx512vnni"
+))]
+pub(crate) fn is_supported() -> bool {
+    true
+}
+
+#[cfg(all(
+    not(all(
+        target_feature = "avx512f",
+        target_feature = "avx512bw",
+        target_feature = "avx512vnni"
+    )),
+    not(feature = "std")
+))]
+pub(crate) fn is_supported() -> bool {
+    super::cpuid::has_avx512vnni()
+}
+
+#[cfg(all(
+    not(all(
+        target_feature = "avx512f",
+        target_feature = "avx512bw",
+        target_feature = "avx512vnni"
+    )),
+    feature = "std"
+))]
+pub(crate) fn is_supported() -> bool {
+    is_x86_feature_detected!("avx512f")
+        && is_x86_feature_detected!("avx512bw")
+        && is_x86_feature_detected!("avx512vnni")
+}
+
+// Weights the bytes and accumulates their sums with a single `vpdpbusd`.
+struct Dpbusd;
+
+impl WeightedSum for Dpbusd {
+    #[inline(always)]
+    unsafe fn add(b_v: __m512i, block: __m512i, v_weights: __m512i) -> __m512i {
+        _mm512_dpbusd_epi32(b_v, block, v_weights)
+    }
+}
+
+#[target_feature(enable = "avx512f,avx512bw,avx512vnni")]
+pub(crate) unsafe fn update_simd(a: u32, b: u32, buf: &[u8]) -> (u32, u32) {
+    avx512::update::<Dpbusd>(a, b, buf)
+}
+
+#[target_feature(enable = "avx512f,avx512bw,avx512vnni")]
+pub(crate) unsafe fn update_x4(states: [(u32, u32); 4], bufs: [&[u8]; 4]) -> [(u32, u32); 4] {
+    avx512::update_x4::<Dpbusd>(states, bufs)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::specialized::avx512::{CHUNK_SIZE, NMAX};
+
+    quickcheck::quickcheck! {
+        fn avx512vnni_is_same_as_baseline(init: u32, buf: Vec<u8>) -> bool {
+            let mut expected = crate::baseline::State::new(init);
+            let mut actual = match super::State::new(init) {
+                Some(state) => state,
+                None => return true,
+            };
+            expected.update(&buf);
+            actual.update(&buf);
+            expected.finalize() == actual.finalize()
+        }
+
+        fn avx512vnni_supports_random_alignment(init: u32, chunks: Vec<(Vec<u8>, usize)>) -> bool {
+            let mut expected = adler32::RollingAdler32::from_value(init);
+            let mut actual = match super::State::new(init) {
+                Some(state) => state,
+                None => return true,
+            };
+            for (chunk, mut offset) in chunks {
+                // Simulate random alignments by offsetting the slice by up to 63 bytes
+                offset &= 0x3f;
+                if chunk.len() <= offset {
+                    expected.update_buffer(&chunk);
+                    actual.update(&chunk);
+                } else {
+                    expected.update_buffer(&chunk[offset..]);
+                    actual.update(&chunk[offset..]);
+                }
+            }
+            expected.hash() == actual.finalize()
+        }
+    }
+
+    #[test]
+    fn avx512vnni_is_valid_for_every_tail_length() {
+        // The tails follow whole blocks, so they are loaded together with bytes to mask out.
+        let v: Vec<u8> = (0..4 * CHUNK_SIZE).map(|i| (i * 37) as u8 | 0x80).collect();
+        for len in 0..=v.len() {
+            let mut expected = crate::baseline::State::new(0xfff0_fff0);
+            let mut actual = match super::State::new(0xfff0_fff0) {
+                Some(state) => state,
+                None => return,
+            };
+            expected.update(&v[..len]);
+            actual.update(&v[..len]);
+            assert_eq!(expected.finalize(), actual.finalize(), "length {}", len);
+        }
+    }
+
+    #[test]
+    fn avx512vnni_is_valid_for_large_input() {
+        let v = vec![100; NMAX * 4];
+        let mut expected = crate::baseline::State::new(1);
+        let mut actual = match super::State::new(1) {
+            Some(state) => state,
+            None => return,
+        };
+        expected.update(&v);
+        actual.update(&v);
+        assert_eq!(expected.finalize(), actual.finalize())
+    }
+
+    #[test]
+    fn avx512vnni_x4_is_valid_for_worst_case_input() {
+        if !super::is_supported() {
+            return;
+        }
+        let v = vec![0xff; NMAX * 4 + 127];
+        let bufs = [&v[..], &v[1..], &v[..NMAX + 65], &v[5..]];
+        let actual = unsafe { super::update_x4([(0xfff0, 0xfff0); 4], bufs) };
+        for (buf, state) in bufs.iter().zip(actual.iter()) {
+            let mut expected = crate::baseline::State::new(0xfff0_fff0);
+            expected.update(buf);
+            assert_eq!(expected.finalize(), state.0 | (state.1 << 16));
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//! CPU feature detection via `cpuid`, for builds without `std` and its
+//! `is_x86_feature_detected!` macro.
+
+#[cfg(target_arch = "x86")]
+use core::arch::x86::{__cpuid, __cpuid_count, has_cpuid, CpuidResult};
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::{__cpuid, __cpuid_count, CpuidResult};
+
+// CPUID.01H:EDX
+#[cfg(any(test, not(target_feature = "sse2")))]
+const SSE2: u32 = 1 << 26;
+// CPUID.01H:ECX
+#[cfg(any(test, not(target_feature = "pclmulqdq")))]
+const PCLMULQDQ: u32 = 1 << 1;
+#[cfg(any(test, not(target_feature = "ssse3")))]
+const SSSE3: u32 = 1 << 9;
+
+// Unused when every feature checked below is enabled at compile time.
+#[allow(dead_code)]
+fn leaf(leaf: u32) -> Option<CpuidResult> {
+    #[cfg(target_arch = "x86")]
+    if !has_cpuid() {
+        return None;
+    }
+    // SAFETY: `cpuid` is available, and leaves above the maximum are never queried. The
+    // intrinsics are only marked safe on newer toolchains.
+    #[allow(unused_unsafe)]
+    unsafe {
+        if __cpuid(0).eax < leaf {
+            return None;
+        }
+        Some(__cpuid_count(leaf, 0))
+    }
+}
+
+// SSE2 is part of the x86_64 targets, so it is only queried on x86.
+#[cfg(any(test, not(target_feature = "sse2")))]
+pub(crate) fn has_sse2() -> bool {
+    leaf(1).is_some_and(|info| info.edx & SSE2 != 0)
+}
+
+#[cfg(any(test, not(target_feature = "ssse3")))]
+pub(crate) fn has_ssse3() -> bool {
+    leaf(1).is_some_and(|info| info.ecx & SSSE3 != 0)
+}
+
+#[cfg(any(test, not(target_feature = "pclmulqdq")))]
+pub(crate) fn has_pclmulqdq() -> bool {
+    leaf(1).is_some_and(|info| info.ecx & PCLMULQDQ != 0)
+}
+
+// The checks for AVX and its XSAVE state. Every one of them is known at compile time once all
+// of the AVX-512 features are enabled.
+#[cfg(any(
+    test,
+    not(all(
+        target_feature = "avx512f",
+        target_feature = "avx512bw",
+        target_feature = "avx512vnni"
+    ))
+))]
+mod avx {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::_xgetbv;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::_xgetbv;
+
+    use super::leaf;
+
+    // CPUID.01H:ECX
+    const OSXSAVE: u32 = 1 << 27;
+    const AVX: u32 = 1 << 28;
+    // CPUID.(EAX=07H,ECX=0):EBX
+    #[cfg(any(test, not(target_feature = "avx2")))]
+    const AVX2: u32 = 1 << 5;
+    const AVX512F: u32 = 1 << 16;
+    const AVX512BW: u32 = 1 << 30;
+    // CPUID.(EAX=07H,ECX=0):ECX
+    const AVX512VNNI: u32 = 1 << 11;
+    // XCR0 bits for SSE and AVX register state.
+    const XCR0_SSE_AVX: u64 = 0b110;
+    // XCR0 bits for AVX-512 opmask and upper ZMM register state.
+    const XCR0_AVX512: u64 = 0b1110_0000;
+
+    #[target_feature(enable = "xsave")]
+    unsafe fn xcr0() -> u64 {
+        _xgetbv(0)
+    }
+
+    // Check that the CPU supports AVX and that the OS saves the given register state on context
+    // switches.
+    fn os_supports(xcr0_mask: u64) -> bool {
+        let info = match leaf(1) {
+            Some(info) => info,
+            None => return false,
+        };
+        if info.ecx & (OSXSAVE | AVX) != OSXSAVE | AVX {
+            return false;
+        }
+        // SAFETY: OSXSAVE indicates that `xgetbv` is supported and enabled.
+        unsafe { xcr0() & xcr0_mask == xcr0_mask }
+    }
+
+    #[cfg(any(test, not(target_feature = "avx2")))]
+    pub(crate) fn has_avx2() -> bool {
+        os_supports(XCR0_SSE_AVX) && leaf(7).is_some_and(|info| info.ebx & AVX2 != 0)
+    }
+
+    pub(crate) fn has_avx512bw() -> bool {
+        os_supports(XCR0_SSE_AVX | XCR0_AVX512)
+            && leaf(7).is_some_and(|info| info.ebx & (AVX512F | AVX512BW) == AVX512F | AVX512BW)
+    }
+
+    pub(crate) fn has_avx512vnni() -> bool {
+        has_avx512bw() && leaf(7).is_some_and(|info| info.ecx & AVX512VNNI != 0)
+    }
+}
+
+#[cfg(any(
+    test,
+    not(all(
+        target_feature = "avx512f",
+        target_feature = "avx512bw",
+        target_feature = "avx512vnni"
+    ))
+))]
+pub(crate) use avx::*;
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn cpuid_matches_std_detection() {
+        assert_eq!(super::has_sse2(), is_x86_feature_detected!("sse2"));
+        assert_eq!(super::has_ssse3(), is_x86_feature_detected!("ssse3"));
+        assert_eq!(
+            super::has_pclmulqdq(),
+            is_x86_feature_detected!("pclmulqdq")
+        );
+        assert_eq!(super::has_avx2(), is_x86_feature_detected!("avx2"));
+        assert_eq!(
+            super::has_avx512bw(),
+            is_x86_feature_detected!("avx512f") && is_x86_feature_detected!("avx512bw")
+        );
+        assert_eq!(
+            super::has_avx512vnni(),
+            super::has_avx512bw() && is_x86_feature_detected!("avx512vnni")
+        );
+    }
+}
@@ -0,0 +1,154 @@
+const NMAX: usize = 5536;
+const CHUNK_SIZE: usize = 32;
+
+// Byte weights for each 32-byte block, in load order.
+const WEIGHTS: [u16; CHUNK_SIZE] = [
+    32, 31, 30, 29, 28, 27, 26, 25, 24, 23, 22, 21, 20, 19, 18, 17, 16, 15, 14, 13, 12, 11, 10, 9,
+    8, 7, 6, 5, 4, 3, 2, 1,
+];
+
+#[derive(Copy, Clone, Debug)]
+pub struct State {
+    state: (u32, u32),
+}
+
+impl State {
+    pub fn new(initial: u32) -> Option<Self> {
+        if is_supported() {
+            // SAFETY: Ensure that all required instructions are supported by the CPU.
+            Some(Self {
+                state: (initial & 0xffff, initial >> 16),
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn finalize(self) -> u32 {
+        self.state.0 | (self.state.1 << 16)
+    }
+
+    pub fn reset(&mut self) {
+        self.state = (1, 0)
+    }
+
+    pub fn update(&mut self, buf: &[u8]) {
+        self.state = unsafe { update_simd(self.state.0, self.state.1, buf) }
+    }
+}
+
+#[cfg(target_feature = "neon")]
+pub(crate) fn is_supported() -> bool {
+    true
+}
+
+#[cfg(all(not(target_feature = "neon"), not(feature = "std")))]
+pub(crate) fn is_supported() -> bool {
+    false
+}
+
+#[cfg(all(not(target_feature = "neon"), feature = "std"))]
+pub(crate) fn is_supported() -> bool {
+    std::arch::is_aarch64_feature_detected!("neon")
+}
+
+#[target_feature(enable = "neon")]
+pub(crate) unsafe fn update_simd(mut a: u32, mut b: u32, buf: &[u8]) -> (u32, u32) {
+    let chunks = buf.chunks_exact(NMAX);
+    let mut remainder = chunks.remainder();
+    for chunk in chunks {
+        add_reduce(&mut a, &mut b, chunk);
+        a = crate::baseline::reduce(a);
+        b = crate::baseline::reduce(b);
+    }
+    remainder = add_reduce(&mut a, &mut b, remainder);
+    crate::baseline::update_tail(a, b, remainder)
+}
+
+#[inline(always)]
+unsafe fn add_reduce<'a>(a: &mut u32, b: &mut u32, chunk: &'a [u8]) -> &'a [u8] {
+    use core::arch::aarch64::*;
+
+    if chunk.len() < CHUNK_SIZE {
+        return chunk;
+    }
+    let inner_chunks = chunk.chunks_exact(CHUNK_SIZE);
+    let remainder = inner_chunks.remainder();
+    *b += *a * (inner_chunks.len() * CHUNK_SIZE) as u32;
+
+    let mut v_s1 = vdupq_n_u32(0);
+    let mut v_s2 = vdupq_n_u32(0);
+    // Per-byte-position column sums. With at most NMAX / CHUNK_SIZE blocks, these cannot
+    // overflow 16 bits.
+    let mut v_column_1 = vdupq_n_u16(0);
+    let mut v_column_2 = vdupq_n_u16(0);
+    let mut v_column_3 = vdupq_n_u16(0);
+    let mut v_column_4 = vdupq_n_u16(0);
+
+    for inner_chunk in inner_chunks {
+        let v_p_left = vld1q_u8(inner_chunk.as_ptr());
+        let v_p_right = vld1q_u8(inner_chunk[16..].as_ptr());
+        v_s2 = vaddq_u32(v_s2, v_s1);
+        v_s1 = vpadalq_u16(v_s1, vpadalq_u8(vpaddlq_u8(v_p_left), v_p_right));
+        v_column_1 = vaddw_u8(v_column_1, vget_low_u8(v_p_left));
+        v_column_2 = vaddw_u8(v_column_2, vget_high_u8(v_p_left));
+        v_column_3 = vaddw_u8(v_column_3, vget_low_u8(v_p_right));
+        v_column_4 = vaddw_u8(v_column_4, vget_high_u8(v_p_right));
+    }
+
+    v_s2 = vshlq_n_u32::<5>(v_s2);
+    let w = WEIGHTS.as_ptr();
+    v_s2 = vmlal_u16(v_s2, vget_low_u16(v_column_1), vld1_u16(w));
+    v_s2 = vmlal_u16(v_s2, vget_high_u16(v_column_1), vld1_u16(w.add(4)));
+    v_s2 = vmlal_u16(v_s2, vget_low_u16(v_column_2), vld1_u16(w.add(8)));
+    v_s2 = vmlal_u16(v_s2, vget_high_u16(v_column_2), vld1_u16(w.add(12)));
+    v_s2 = vmlal_u16(v_s2, vget_low_u16(v_column_3), vld1_u16(w.add(16)));
+    v_s2 = vmlal_u16(v_s2, vget_high_u16(v_column_3), vld1_u16(w.add(20)));
+    v_s2 = vmlal_u16(v_s2, vget_low_u16(v_column_4), vld1_u16(w.add(24)));
+    v_s2 = vmlal_u16(v_s2, vget_high_u16(v_column_4), vld1_u16(w.add(28)));
+
+    *a += vaddvq_u32(v_s1);
+    *b += vaddvq_u32(v_s2);
+
+    remainder
+}
+
+#[cfg(test)]
+mod tests {
+    quickcheck::quickcheck! {
+        fn neon_is_same_as_baseline(init: u32, buf: Vec<u8>) -> bool {
+            let mut expected = crate::baseline::State::new(init);
+            let mut actual = super::State::new(init).expect("neon not supported");
+            expected.update(&buf);
+            actual.update(&buf);
+            expected.finalize() == actual.finalize()
+        }
+
+        fn neon_supports_random_alignment(init: u32, chunks: Vec<(Vec<u8>, usize)>) -> bool {
+            let mut expected = adler32::RollingAdler32::from_value(init);
+            let mut actual = super::State::new(init).expect("neon not supported");
+            for (chunk, mut offset) in chunks {
+                // Simulate random alignments by offsetting the slice by up to 15 bytes
+                offset &= 0xf;
+                if chunk.len() <= offset {
+                    expected.update_buffer(&chunk);
+                    actual.update(&chunk);
+                } else {
+                    expected.update_buffer(&chunk[offset..]);
+                    actual.update(&chunk[offset..]);
+                }
+            }
+            expected.hash() == actual.finalize()
+        }
+    }
+
+    #[test]
+    fn neon_is_valid_for_large_input() {
+        let v = vec![100; super::NMAX * 4];
+        let mut expected = crate::baseline::State::new(1);
+        let mut actual = super::State::new(1).expect("neon not supported");
+        expected.update(&v);
+        actual.update(&v);
+        assert_eq!(expected.finalize(), actual.finalize())
+    }
+}
@@ -0,0 +1,150 @@
+//! CRC-32 (IEEE) folding with carry-less multiplication, after Intel's "Fast CRC Computation
+//! for Generic Polynomials Using PCLMULQDQ Instruction". The constants are for the bit-reflected
+//! polynomial; `K1`/`K2` fold by 512 bits and `K3`/`K4` by 128 bits.
+
+#[cfg(target_arch = "x86")]
+use core::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+
+const K1: i64 = 0x1_5444_2bd4;
+const K2: i64 = 0x1_c6e4_1596;
+const K3: i64 = 0x1_7519_97d0;
+const K4: i64 = 0x0_ccaa_009e;
+const K5: i64 = 0x1_63cd_6124;
+const P_X: i64 = 0x1_db71_0641;
+const U_PRIME: i64 = 0x1_f701_1641;
+// The number of bytes folded per call to `Folder::fold`.
+pub(crate) const FOLD_SIZE: usize = 64;
+
+#[cfg(target_feature = "pclmulqdq")]
+pub(crate) fn is_supported() -> bool {
+    true
+}
+
+#[cfg(all(not(target_feature = "pclmulqdq"), not(feature = "std")))]
+pub(crate) fn is_supported() -> bool {
+    super::cpuid::has_pclmulqdq()
+}
+
+#[cfg(all(not(target_feature = "pclmulqdq"), feature = "std"))]
+pub(crate) fn is_supported() -> bool {
+    is_x86_feature_detected!("pclmulqdq")
+}
+
+#[target_feature(enable = "pclmulqdq")]
+pub(crate) unsafe fn update_simd(crc: u32, buf: &[u8]) -> u32 {
+    if buf.len() < FOLD_SIZE {
+        return crate::crc32::update_scalar(crc, buf);
+    }
+    let (head, rest) = buf.split_at(FOLD_SIZE);
+    let mut folder = Folder::new(crc, head);
+    let chunks = rest.chunks_exact(FOLD_SIZE);
+    let remainder = chunks.remainder();
+    for chunk in chunks {
+        folder.fold(chunk);
+    }
+    folder.finish(remainder)
+}
+
+/// The state of a CRC-32 computation that folds the input `FOLD_SIZE` bytes at a time, so that
+/// it can be interleaved with the Adler-32 loops.
+pub(crate) struct Folder {
+    x: [__m128i; 4],
+}
+
+impl Folder {
+    /// Start folding from `crc`, given the first `FOLD_SIZE` bytes of the input.
+    #[inline(always)]
+    pub(crate) unsafe fn new(crc: u32, buf: &[u8]) -> Self {
+        let mut x = load4(buf);
+        x[0] = _mm_xor_si128(x[0], _mm_cvtsi32_si128(!crc as i32));
+        Self { x }
+    }
+
+    /// Fold in the next `FOLD_SIZE` bytes of the input.
+    #[inline(always)]
+    pub(crate) unsafe fn fold(&mut self, buf: &[u8]) {
+        self.fold_vectors(load4(buf));
+    }
+
+    /// Fold in the next `FOLD_SIZE` bytes of the input, given as the four vectors that a caller
+    /// has already loaded, in order.
+    #[inline(always)]
+    pub(crate) unsafe fn fold_vectors(&mut self, data: [__m128i; 4]) {
+        let k1k2 = _mm_set_epi64x(K2, K1);
+        for (x, data) in self.x.iter_mut().zip(data.iter()) {
+            *x = fold(*x, *data, k1k2);
+        }
+    }
+
+    /// Fold in the rest of the input, which may have any length, and return the CRC-32.
+    #[inline(always)]
+    pub(crate) unsafe fn finish(self, buf: &[u8]) -> u32 {
+        let k3k4 = _mm_set_epi64x(K4, K3);
+        let mut x = fold(self.x[0], self.x[1], k3k4);
+        x = fold(x, self.x[2], k3k4);
+        x = fold(x, self.x[3], k3k4);
+        let chunks = buf.chunks_exact(16);
+        let remainder = chunks.remainder();
+        for chunk in chunks {
+            x = fold(x, _mm_loadu_si128(chunk.as_ptr() as *const __m128i), k3k4);
+        }
+
+        // Reduce the 128 bits to 64, then to 32 with a Barrett reduction.
+        let mask32 = _mm_set_epi32(0, 0, 0, !0);
+        x = _mm_xor_si128(_mm_clmulepi64_si128(x, k3k4, 0x10), _mm_srli_si128(x, 8));
+        x = _mm_xor_si128(
+            _mm_clmulepi64_si128(_mm_and_si128(x, mask32), _mm_set_epi64x(0, K5), 0x00),
+            _mm_srli_si128(x, 4),
+        );
+        let pu = _mm_set_epi64x(U_PRIME, P_X);
+        let t1 = _mm_clmulepi64_si128(_mm_and_si128(x, mask32), pu, 0x10);
+        let t2 = _mm_clmulepi64_si128(_mm_and_si128(t1, mask32), pu, 0x00);
+        // The input is bit-reflected, so the result is in the upper half of the low 64 bits.
+        let crc = _mm_cvtsi128_si32(_mm_srli_si128(_mm_xor_si128(x, t2), 4)) as u32;
+        crate::crc32::update_scalar(!crc, remainder)
+    }
+}
+
+#[inline(always)]
+unsafe fn load4(buf: &[u8]) -> [__m128i; 4] {
+    let buf = &buf[..FOLD_SIZE];
+    [
+        _mm_loadu_si128(buf.as_ptr() as *const __m128i),
+        _mm_loadu_si128(buf[16..].as_ptr() as *const __m128i),
+        _mm_loadu_si128(buf[32..].as_ptr() as *const __m128i),
+        _mm_loadu_si128(buf[48..].as_ptr() as *const __m128i),
+    ]
+}
+
+// Fold `x` forward over `data` by the distance encoded in `keys`.
+#[inline(always)]
+unsafe fn fold(x: __m128i, data: __m128i, keys: __m128i) -> __m128i {
+    let lo = _mm_clmulepi64_si128(x, keys, 0x00);
+    let hi = _mm_clmulepi64_si128(x, keys, 0x11);
+    _mm_xor_si128(data, _mm_xor_si128(lo, hi))
+}
+
+#[cfg(test)]
+mod tests {
+    quickcheck::quickcheck! {
+        fn pclmulqdq_is_same_as_reference(init: u32, buf: Vec<u8>) -> bool {
+            assert!(super::is_supported(), "pclmulqdq not supported");
+            let mut expected = crc32fast::Hasher::new_with_initial(init);
+            expected.update(&buf);
+            expected.finalize() == unsafe { super::update_simd(init, &buf) }
+        }
+    }
+
+    #[test]
+    fn pclmulqdq_is_valid_for_large_input() {
+        let v: Vec<u8> = (0..100_000u32).map(|i| (i * 31 % 251) as u8).collect();
+        for &len in &[64, 65, 127, 128, 1000, 100_000] {
+            let mut expected = crc32fast::Hasher::new_with_initial(0xdead_beef);
+            expected.update(&v[..len]);
+            let actual = unsafe { super::update_simd(0xdead_beef, &v[..len]) };
+            assert_eq!(expected.finalize(), actual, "{}", len);
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use core::simd::{num::SimdUint, Simd};
+
+const NMAX: usize = 5536;
+const CHUNK_SIZE: usize = 32;
+
+const WEIGHTS: Simd<u32, CHUNK_SIZE> = Simd::from_array([
+    32, 31, 30, 29, 28, 27, 26, 25, 24, 23, 22, 21, 20, 19, 18, 17, 16, 15, 14, 13, 12, 11, 10, 9,
+    8, 7, 6, 5, 4, 3, 2, 1,
+]);
+
+#[derive(Copy, Clone, Debug)]
+pub struct State {
+    state: (u32, u32),
+}
+
+impl State {
+    pub fn new(initial: u32) -> Option<Self> {
+        if is_supported() {
+            Some(Self {
+                state: (initial & 0xffff, initial >> 16),
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn finalize(self) -> u32 {
+        self.state.0 | (self.state.1 << 16)
+    }
+
+    pub fn reset(&mut self) {
+        self.state = (1, 0)
+    }
+
+    pub fn update(&mut self, buf: &[u8]) {
+        self.state = update_simd(self.state.0, self.state.1, buf)
+    }
+}
+
+pub(crate) fn is_supported() -> bool {
+    true
+}
+
+pub(crate) fn update_simd(mut a: u32, mut b: u32, buf: &[u8]) -> (u32, u32) {
+    let chunks = buf.chunks_exact(NMAX);
+    let mut remainder = chunks.remainder();
+    for chunk in chunks {
+        add_reduce(&mut a, &mut b, chunk);
+        a = crate::baseline::reduce(a);
+        b = crate::baseline::reduce(b);
+    }
+    remainder = add_reduce(&mut a, &mut b, remainder);
+    crate::baseline::update_tail(a, b, remainder)
+}
+
+#[inline(always)]
+fn add_reduce<'a>(a: &mut u32, b: &mut u32, chunk: &'a [u8]) -> &'a [u8] {
+    if chunk.len() < CHUNK_SIZE {
+        return chunk;
+    }
+    let v_num_iterate_bytes = (chunk.len() & 0xffff_ffe0) as u32;
+    *b += *a * v_num_iterate_bytes;
+    // `v_s1` holds the per-column byte sums, and `v_s2` the sum of `v_s1` before each block.
+    let mut v_s1 = Simd::<u32, CHUNK_SIZE>::splat(0);
+    let mut v_s2 = Simd::<u32, CHUNK_SIZE>::splat(0);
+
+    let inner_chunks = chunk.chunks_exact(CHUNK_SIZE);
+    let remainder = inner_chunks.remainder();
+    for inner_chunk in inner_chunks {
+        let v_p = Simd::<u8, CHUNK_SIZE>::from_slice(inner_chunk);
+        v_s2 += v_s1;
+        v_s1 += v_p.cast::<u32>();
+    }
+    *a += v_s1.reduce_sum();
+    *b += (v_s2.reduce_sum() << 5) + (v_s1 * WEIGHTS).reduce_sum();
+
+    remainder
+}
+
+#[cfg(test)]
+mod tests {
+    quickcheck::quickcheck! {
+        fn portable_is_same_as_baseline(init: u32, buf: Vec<u8>) -> bool {
+            let mut expected = crate::baseline::State::new(init);
+            let mut actual = super::State::new(init).expect("portable not supported");
+            expected.update(&buf);
+            actual.update(&buf);
+            expected.finalize() == actual.finalize()
+        }
+
+        fn portable_supports_random_alignment(init: u32, chunks: Vec<(Vec<u8>, usize)>) -> bool {
+            let mut expected = adler32::RollingAdler32::from_value(init);
+            let mut actual = super::State::new(init).expect("portable not supported");
+            for (chunk, mut offset) in chunks {
+                // Simulate random alignments by offsetting the slice by up to 31 bytes
+                offset &= 0x1f;
+                if chunk.len() <= offset {
+                    expected.update_buffer(&chunk);
+                    actual.update(&chunk);
+                } else {
+                    expected.update_buffer(&chunk[offset..]);
+                    actual.update(&chunk[offset..]);
+                }
+            }
+            expected.hash() == actual.finalize()
+        }
+    }
+
+    #[test]
+    fn portable_is_valid_for_large_input() {
+        let v = vec![100; super::NMAX * 4];
+        let mut expected = crate::baseline::State::new(1);
+        let mut actual = super::State::new(1).expect("portable not supported");
+        expected.update(&v);
+        actual.update(&v);
+        assert_eq!(expected.finalize(), actual.finalize())
+    }
+}
@@ -0,0 +1,199 @@
+use core::arch::asm;
+
+const NMAX: usize = 5552;
+// Caps the strip length so that the weights below stay small on very wide implementations.
+const MAX_STRIP_LEN: usize = 256;
+
+#[derive(Copy, Clone, Debug)]
+pub struct State {
+    state: (u32, u32),
+}
+
+impl State {
+    pub fn new(initial: u32) -> Option<Self> {
+        if is_supported() {
+            // SAFETY: Ensure that all required instructions are supported by the CPU.
+            Some(Self {
+                state: (initial & 0xffff, initial >> 16),
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn finalize(self) -> u32 {
+        self.state.0 | (self.state.1 << 16)
+    }
+
+    pub fn reset(&mut self) {
+        self.state = (1, 0)
+    }
+
+    pub fn update(&mut self, buf: &[u8]) {
+        self.state = unsafe { update_simd(self.state.0, self.state.1, buf) }
+    }
+}
+
+#[cfg(target_feature = "v")]
+pub(crate) fn is_supported() -> bool {
+    true
+}
+
+#[cfg(all(not(target_feature = "v"), not(feature = "std")))]
+pub(crate) fn is_supported() -> bool {
+    false
+}
+
+#[cfg(all(not(target_feature = "v"), feature = "std"))]
+pub(crate) fn is_supported() -> bool {
+    std::arch::is_riscv_feature_detected!("v")
+}
+
+#[target_feature(enable = "v")]
+pub(crate) unsafe fn update_simd(mut a: u32, mut b: u32, buf: &[u8]) -> (u32, u32) {
+    // The strip length depends on VLEN, so each block is the largest multiple of it that fits
+    // in `NMAX`. The sums at every reduction then match `baseline` exactly.
+    let strip_len = strip_len();
+    let block_len = NMAX - NMAX % strip_len;
+    let chunks = buf.chunks_exact(block_len);
+    let mut remainder = chunks.remainder();
+    for chunk in chunks {
+        add_reduce(&mut a, &mut b, chunk, strip_len);
+        a = crate::baseline::reduce(a);
+        b = crate::baseline::reduce(b);
+    }
+    remainder = add_reduce(&mut a, &mut b, remainder, strip_len);
+    crate::baseline::update_tail(a, b, remainder)
+}
+
+// Return the number of bytes loaded per iteration, which is VLMAX for `e8, m1` up to
+// `MAX_STRIP_LEN`. VLEN is at least 128 bits, so this is at least 16.
+#[inline(always)]
+unsafe fn strip_len() -> usize {
+    let vlmax: usize;
+    asm!(
+        "vsetvli {vlmax}, zero, e8, m1, ta, ma",
+        vlmax = out(reg) vlmax,
+        options(nomem, nostack),
+    );
+    vlmax.min(MAX_STRIP_LEN)
+}
+
+#[inline(always)]
+unsafe fn add_reduce<'a>(a: &mut u32, b: &mut u32, chunk: &'a [u8], strip_len: usize) -> &'a [u8] {
+    let strips = chunk.len() / strip_len;
+    if strips == 0 {
+        return chunk;
+    }
+    let len = strips * strip_len;
+    *b += *a * len as u32;
+    let s1: usize;
+    let s2: usize;
+    let weighted: usize;
+    // `v20` holds the per-column byte sums, and `v24` the sum of `v20` before each strip. The
+    // bytes are zero-extended to 32 bits, so neither can overflow within a block.
+    asm!(
+        "vsetvli zero, {vl}, e32, m4, ta, ma",
+        "vmv.v.i v20, 0",
+        "vmv.v.i v24, 0",
+        "2:",
+        "vsetvli zero, {vl}, e8, m1, ta, ma",
+        "vle8.v v8, ({p})",
+        "add {p}, {p}, {vl}",
+        "vsetvli zero, {vl}, e32, m4, ta, ma",
+        "vzext.vf4 v16, v8",
+        "vadd.vv v24, v24, v20",
+        "vadd.vv v20, v20, v16",
+        "addi {n}, {n}, -1",
+        "bnez {n}, 2b",
+        // Weight each column by its distance from the end of the strip.
+        "vid.v v16",
+        "vrsub.vx v16, v16, {vl}",
+        "vmul.vv v16, v16, v20",
+        "vmv.s.x v12, zero",
+        "vredsum.vs v13, v20, v12",
+        "vredsum.vs v14, v24, v12",
+        "vredsum.vs v15, v16, v12",
+        "vmv.x.s {s1}, v13",
+        "vmv.x.s {s2}, v14",
+        "vmv.x.s {weighted}, v15",
+        vl = in(reg) strip_len,
+        p = inout(reg) chunk.as_ptr() => _,
+        n = inout(reg) strips => _,
+        s1 = out(reg) s1,
+        s2 = out(reg) s2,
+        weighted = out(reg) weighted,
+        out("v8") _,
+        out("v12") _,
+        out("v13") _,
+        out("v14") _,
+        out("v15") _,
+        out("v16") _,
+        out("v17") _,
+        out("v18") _,
+        out("v19") _,
+        out("v20") _,
+        out("v21") _,
+        out("v22") _,
+        out("v23") _,
+        out("v24") _,
+        out("v25") _,
+        out("v26") _,
+        out("v27") _,
+        options(readonly, nostack),
+    );
+    // `vmv.x.s` sign-extends, so only the low 32 bits are meaningful.
+    *a += s1 as u32;
+    *b += (s2 as u32) * strip_len as u32 + weighted as u32;
+
+    &chunk[len..]
+}
+
+#[cfg(test)]
+mod tests {
+    quickcheck::quickcheck! {
+        fn rvv_is_same_as_baseline(init: u32, buf: Vec<u8>) -> bool {
+            let mut expected = crate::baseline::State::new(init);
+            let mut actual = match super::State::new(init) {
+                Some(state) => state,
+                None => return true,
+            };
+            expected.update(&buf);
+            actual.update(&buf);
+            expected.finalize() == actual.finalize()
+        }
+
+        fn rvv_supports_random_alignment(init: u32, chunks: Vec<(Vec<u8>, usize)>) -> bool {
+            let mut expected = adler32::RollingAdler32::from_value(init);
+            let mut actual = match super::State::new(init) {
+                Some(state) => state,
+                None => return true,
+            };
+            for (chunk, mut offset) in chunks {
+                // Simulate random alignments by offsetting the slice by up to 63 bytes
+                offset &= 0x3f;
+                if chunk.len() <= offset {
+                    expected.update_buffer(&chunk);
+                    actual.update(&chunk);
+                } else {
+                    expected.update_buffer(&chunk[offset..]);
+                    actual.update(&chunk[offset..]);
+                }
+            }
+            expected.hash() == actual.finalize()
+        }
+    }
+
+    #[test]
+    fn rvv_is_valid_for_large_input() {
+        let v = vec![0xff; super::NMAX * 4 + 17];
+        let mut expected = crate::baseline::State::new(0xfff0_fff0);
+        let mut actual = match super::State::new(0xfff0_fff0) {
+            Some(state) => state,
+            None => return,
+        };
+        expected.update(&v);
+        actual.update(&v);
+        assert_eq!(expected.finalize(), actual.finalize())
+    }
+}
@@ -0,0 +1,150 @@
+//! The WebAssembly backend. WebAssembly has no runtime feature detection, and a module using
+//! SIMD instructions fails validation on engines without support, so this is only compiled when
+//! `simd128` is enabled at compile time.
+
+const NMAX: usize = 5536;
+const CHUNK_SIZE: usize = 32;
+
+#[derive(Copy, Clone, Debug)]
+pub struct State {
+    state: (u32, u32),
+}
+
+impl State {
+    pub fn new(initial: u32) -> Option<Self> {
+        if is_supported() {
+            // SAFETY: Ensure that all required instructions are supported by the CPU.
+            Some(Self {
+                state: (initial & 0xffff, initial >> 16),
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn finalize(self) -> u32 {
+        self.state.0 | (self.state.1 << 16)
+    }
+
+    pub fn reset(&mut self) {
+        self.state = (1, 0)
+    }
+
+    pub fn update(&mut self, buf: &[u8]) {
+        self.state = unsafe { update_simd(self.state.0, self.state.1, buf) }
+    }
+}
+
+pub(crate) fn is_supported() -> bool {
+    cfg!(target_feature = "simd128")
+}
+
+pub(crate) unsafe fn update_simd(mut a: u32, mut b: u32, buf: &[u8]) -> (u32, u32) {
+    let chunks = buf.chunks_exact(NMAX);
+    let mut remainder = chunks.remainder();
+    for chunk in chunks {
+        add_reduce(&mut a, &mut b, chunk);
+        a = crate::baseline::reduce(a);
+        b = crate::baseline::reduce(b);
+    }
+    remainder = add_reduce(&mut a, &mut b, remainder);
+    crate::baseline::update_tail(a, b, remainder)
+}
+
+#[inline(always)]
+unsafe fn add_reduce<'a>(a: &mut u32, b: &mut u32, chunk: &'a [u8]) -> &'a [u8] {
+    use core::arch::wasm32::*;
+
+    if chunk.len() < CHUNK_SIZE {
+        return chunk;
+    }
+    let v_weights_left = u8x16(
+        32, 31, 30, 29, 28, 27, 26, 25, 24, 23, 22, 21, 20, 19, 18, 17,
+    );
+    let v_weights_right = u8x16(16, 15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1);
+
+    let inner_chunks = chunk.chunks_exact(CHUNK_SIZE);
+    let remainder = inner_chunks.remainder();
+    *b += *a * (inner_chunks.len() * CHUNK_SIZE) as u32;
+    let mut v_s1 = u32x4_splat(0);
+    let mut v_s2j = u32x4_splat(0);
+    let mut v_s2k = u32x4_splat(0);
+
+    for inner_chunk in inner_chunks {
+        let v_p_left = v128_load(inner_chunk.as_ptr() as *const v128);
+        let v_p_right = v128_load(inner_chunk[16..].as_ptr() as *const v128);
+        v_s2j = i32x4_add(v_s2j, v_s1);
+        let v_sum = i16x8_add(
+            u16x8_extadd_pairwise_u8x16(v_p_left),
+            u16x8_extadd_pairwise_u8x16(v_p_right),
+        );
+        v_s1 = i32x4_add(v_s1, u32x4_extadd_pairwise_u16x8(v_sum));
+        // Each product is at most 255 * 32, so the pairwise sums fit in 16 bits.
+        let v_mad_left = i16x8_add(
+            u16x8_extmul_low_u8x16(v_p_left, v_weights_left),
+            u16x8_extmul_high_u8x16(v_p_left, v_weights_left),
+        );
+        let v_mad_right = i16x8_add(
+            u16x8_extmul_low_u8x16(v_p_right, v_weights_right),
+            u16x8_extmul_high_u8x16(v_p_right, v_weights_right),
+        );
+        v_s2k = i32x4_add(v_s2k, u32x4_extadd_pairwise_u16x8(v_mad_left));
+        v_s2k = i32x4_add(v_s2k, u32x4_extadd_pairwise_u16x8(v_mad_right));
+    }
+
+    let v_s2 = i32x4_add(v_s2k, i32x4_shl(v_s2j, 5));
+    *a += horizontal_sum(v_s1);
+    *b += horizontal_sum(v_s2);
+
+    remainder
+}
+
+#[inline(always)]
+fn horizontal_sum(v: core::arch::wasm32::v128) -> u32 {
+    use core::arch::wasm32::*;
+
+    u32x4_extract_lane::<0>(v)
+        .wrapping_add(u32x4_extract_lane::<1>(v))
+        .wrapping_add(u32x4_extract_lane::<2>(v))
+        .wrapping_add(u32x4_extract_lane::<3>(v))
+}
+
+#[cfg(test)]
+mod tests {
+    quickcheck::quickcheck! {
+        fn simd128_is_same_as_baseline(init: u32, buf: Vec<u8>) -> bool {
+            let mut expected = crate::baseline::State::new(init);
+            let mut actual = super::State::new(init).expect("simd128 not supported");
+            expected.update(&buf);
+            actual.update(&buf);
+            expected.finalize() == actual.finalize()
+        }
+
+        fn simd128_supports_random_alignment(init: u32, chunks: Vec<(Vec<u8>, usize)>) -> bool {
+            let mut expected = adler32::RollingAdler32::from_value(init);
+            let mut actual = super::State::new(init).expect("simd128 not supported");
+            for (chunk, mut offset) in chunks {
+                // Simulate random alignments by offsetting the slice by up to 15 bytes
+                offset &= 0xf;
+                if chunk.len() <= offset {
+                    expected.update_buffer(&chunk);
+                    actual.update(&chunk);
+                } else {
+                    expected.update_buffer(&chunk[offset..]);
+                    actual.update(&chunk[offset..]);
+                }
+            }
+            expected.hash() == actual.finalize()
+        }
+    }
+
+    #[test]
+    fn simd128_is_valid_for_large_input() {
+        let v = vec![100; super::NMAX * 4];
+        let mut expected = crate::baseline::State::new(1);
+        let mut actual = super::State::new(1).expect("simd128 not supported");
+        expected.update(&v);
+        actual.update(&v);
+        assert_eq!(expected.finalize(), actual.finalize())
+    }
+}
@@ -0,0 +1,240 @@
+const NMAX: usize = 5536;
+const CHUNK_SIZE: usize = 32;
+
+#[derive(Copy, Clone, Debug)]
+pub struct State {
+    state: (u32, u32),
+}
+
+impl State {
+    pub fn new(initial: u32) -> Option<Self> {
+        if is_supported() {
+            // SAFETY: Ensure that all required instructions are supported by the CPU.
+            Some(Self {
+                state: (initial & 0xffff, initial >> 16),
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn finalize(self) -> u32 {
+        self.state.0 | (self.state.1 << 16)
+    }
+
+    pub fn reset(&mut self) {
+        self.state = (1, 0)
+    }
+
+    pub fn update(&mut self, buf: &[u8]) {
+        self.state = unsafe { update_simd(self.state.0, self.state.1, buf) }
+    }
+}
+
+#[cfg(target_feature = "sse2")]
+pub(crate) fn is_supported() -> bool {
+    true
+}
+
+#[cfg(all(not(target_feature = "sse2"), not(feature = "std")))]
+pub(crate) fn is_supported() -> bool {
+    super::cpuid::has_sse2()
+}
+
+#[cfg(all(not(target_feature = "sse2"), feature = "std"))]
+pub(crate) fn is_supported() -> bool {
+    is_x86_feature_detected!("sse2")
+}
+
+#[target_feature(enable = "sse2")]
+pub(crate) unsafe fn update_simd(mut a: u32, mut b: u32, buf: &[u8]) -> (u32, u32) {
+    let chunks = buf.chunks_exact(NMAX);
+    let mut remainder = chunks.remainder();
+    for chunk in chunks {
+        add_reduce(&mut a, &mut b, chunk);
+        a = crate::baseline::reduce(a);
+        b = crate::baseline::reduce(b);
+    }
+    remainder = add_reduce(&mut a, &mut b, remainder);
+    update_tail(a, b, buf, remainder.len())
+}
+
+#[inline(always)]
+unsafe fn add_reduce<'a>(a: &mut u32, b: &mut u32, chunk: &'a [u8]) -> &'a [u8] {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    if chunk.len() < CHUNK_SIZE {
+        return chunk;
+    }
+    let v_zeroes = _mm_setzero_si128();
+    // Without `pmaddubsw`, the bytes are widened to 16 bits and weighted with `pmaddwd`.
+    let v_weights_left_lo = _mm_set_epi16(25, 26, 27, 28, 29, 30, 31, 32);
+    let v_weights_left_hi = _mm_set_epi16(17, 18, 19, 20, 21, 22, 23, 24);
+    let v_weights_right_lo = _mm_set_epi16(9, 10, 11, 12, 13, 14, 15, 16);
+    let v_weights_right_hi = _mm_set_epi16(1, 2, 3, 4, 5, 6, 7, 8);
+
+    let v_num_iterate_bytes = (chunk.len() & 0xffff_ffe0) as u32;
+    *b += *a * v_num_iterate_bytes;
+    let mut v_v1 = _mm_setzero_si128();
+    let mut v_v2j = _mm_setzero_si128();
+    let mut v_v2k = _mm_setzero_si128();
+
+    let inner_chunks = chunk.chunks_exact(CHUNK_SIZE);
+    let remainder = inner_chunks.remainder();
+    for inner_chunk in inner_chunks {
+        let v_p_left = _mm_loadu_si128(inner_chunk.as_ptr() as *const __m128i);
+        let v_p_right = _mm_loadu_si128(inner_chunk[16..].as_ptr() as *const __m128i);
+        v_v2j = _mm_add_epi32(v_v2j, v_v1);
+        v_v1 = _mm_add_epi32(v_v1, _mm_sad_epu8(v_p_left, v_zeroes));
+        v_v1 = _mm_add_epi32(v_v1, _mm_sad_epu8(v_p_right, v_zeroes));
+        v_v2k = _mm_add_epi32(
+            v_v2k,
+            _mm_madd_epi16(_mm_unpacklo_epi8(v_p_left, v_zeroes), v_weights_left_lo),
+        );
+        v_v2k = _mm_add_epi32(
+            v_v2k,
+            _mm_madd_epi16(_mm_unpackhi_epi8(v_p_left, v_zeroes), v_weights_left_hi),
+        );
+        v_v2k = _mm_add_epi32(
+            v_v2k,
+            _mm_madd_epi16(_mm_unpacklo_epi8(v_p_right, v_zeroes), v_weights_right_lo),
+        );
+        v_v2k = _mm_add_epi32(
+            v_v2k,
+            _mm_madd_epi16(_mm_unpackhi_epi8(v_p_right, v_zeroes), v_weights_right_hi),
+        );
+    }
+    v_v1 = _mm_add_epi32(v_v1, _mm_shuffle_epi32(v_v1, 177));
+    v_v1 = _mm_add_epi32(v_v1, _mm_shuffle_epi32(v_v1, 78));
+    *a += _mm_cvtsi128_si32(v_v1) as u32;
+
+    let mut v_v2 = _mm_add_epi32(v_v2k, _mm_slli_epi32(v_v2j, 5));
+    v_v2 = _mm_add_epi32(v_v2, _mm_shuffle_epi32(v_v2, 177));
+    v_v2 = _mm_add_epi32(v_v2, _mm_shuffle_epi32(v_v2, 78));
+    *b += _mm_cvtsi128_si32(v_v2) as u32;
+
+    remainder
+}
+
+// Process the last `len` bytes of `buf`, fewer than `CHUNK_SIZE`, and reduce the sums. The block
+// is loaded so that it ends at the end of `buf`, with the bytes before the tail masked out, so the
+// weights of the tail still count down to 1. Inputs shorter than a block use the scalar tail.
+#[inline(always)]
+unsafe fn update_tail(mut a: u32, mut b: u32, buf: &[u8], len: usize) -> (u32, u32) {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    if buf.len() < CHUNK_SIZE || len == 0 {
+        return crate::baseline::update_tail(a, b, &buf[buf.len() - len..]);
+    }
+    let v_zeroes = _mm_setzero_si128();
+    let v_weights_left_lo = _mm_set_epi16(25, 26, 27, 28, 29, 30, 31, 32);
+    let v_weights_left_hi = _mm_set_epi16(17, 18, 19, 20, 21, 22, 23, 24);
+    let v_weights_right_lo = _mm_set_epi16(9, 10, 11, 12, 13, 14, 15, 16);
+    let v_weights_right_hi = _mm_set_epi16(1, 2, 3, 4, 5, 6, 7, 8);
+    // The byte weights again, to select the bytes of the tail.
+    let v_len = _mm_set1_epi8(len as i8 + 1);
+    let v_mask_left = _mm_cmpgt_epi8(
+        v_len,
+        _mm_set_epi8(
+            17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32,
+        ),
+    );
+    let v_mask_right = _mm_cmpgt_epi8(
+        v_len,
+        _mm_set_epi8(1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16),
+    );
+
+    let block = &buf[buf.len() - CHUNK_SIZE..];
+    let v_p_left = _mm_and_si128(
+        _mm_loadu_si128(block.as_ptr() as *const __m128i),
+        v_mask_left,
+    );
+    let v_p_right = _mm_and_si128(
+        _mm_loadu_si128(block[16..].as_ptr() as *const __m128i),
+        v_mask_right,
+    );
+    let mut v_v1 = _mm_add_epi32(
+        _mm_sad_epu8(v_p_left, v_zeroes),
+        _mm_sad_epu8(v_p_right, v_zeroes),
+    );
+    let mut v_v2 = _mm_add_epi32(
+        _mm_add_epi32(
+            _mm_madd_epi16(_mm_unpacklo_epi8(v_p_left, v_zeroes), v_weights_left_lo),
+            _mm_madd_epi16(_mm_unpackhi_epi8(v_p_left, v_zeroes), v_weights_left_hi),
+        ),
+        _mm_add_epi32(
+            _mm_madd_epi16(_mm_unpacklo_epi8(v_p_right, v_zeroes), v_weights_right_lo),
+            _mm_madd_epi16(_mm_unpackhi_epi8(v_p_right, v_zeroes), v_weights_right_hi),
+        ),
+    );
+    v_v1 = _mm_add_epi32(v_v1, _mm_shuffle_epi32(v_v1, 78));
+    b += a * len as u32;
+    a += _mm_cvtsi128_si32(v_v1) as u32;
+
+    v_v2 = _mm_add_epi32(v_v2, _mm_shuffle_epi32(v_v2, 177));
+    v_v2 = _mm_add_epi32(v_v2, _mm_shuffle_epi32(v_v2, 78));
+    b += _mm_cvtsi128_si32(v_v2) as u32;
+
+    (crate::baseline::reduce(a), crate::baseline::reduce(b))
+}
+
+#[cfg(test)]
+mod tests {
+    quickcheck::quickcheck! {
+        fn sse2_is_same_as_baseline(init: u32, buf: Vec<u8>) -> bool {
+            let mut expected = crate::baseline::State::new(init);
+            let mut actual = super::State::new(init).expect("sse2 not supported");
+            expected.update(&buf);
+            actual.update(&buf);
+            expected.finalize() == actual.finalize()
+        }
+
+        fn sse2_supports_random_alignment(init: u32, chunks: Vec<(Vec<u8>, usize)>) -> bool {
+            let mut expected = adler32::RollingAdler32::from_value(init);
+            let mut actual = super::State::new(init).expect("sse2 not supported");
+            for (chunk, mut offset) in chunks {
+                // Simulate random alignments by offsetting the slice by up to 15 bytes
+                offset &= 0xf;
+                if chunk.len() <= offset {
+                    expected.update_buffer(&chunk);
+                    actual.update(&chunk);
+                } else {
+                    expected.update_buffer(&chunk[offset..]);
+                    actual.update(&chunk[offset..]);
+                }
+            }
+            expected.hash() == actual.finalize()
+        }
+    }
+
+    #[test]
+    fn sse2_is_valid_for_every_tail_length() {
+        // The tails follow whole blocks, so they are loaded together with bytes to mask out.
+        let v: Vec<u8> = (0..4 * super::CHUNK_SIZE)
+            .map(|i| (i * 37) as u8 | 0x80)
+            .collect();
+        for len in 0..=v.len() {
+            let mut expected = crate::baseline::State::new(0xfff0_fff0);
+            let mut actual = super::State::new(0xfff0_fff0).expect("sse2 not supported");
+            expected.update(&v[..len]);
+            actual.update(&v[..len]);
+            assert_eq!(expected.finalize(), actual.finalize(), "length {}", len);
+        }
+    }
+
+    #[test]
+    fn sse2_is_valid_for_large_input() {
+        let v = vec![100; super::NMAX * 4];
+        let mut expected = crate::baseline::State::new(1);
+        let mut actual = super::State::new(1).expect("sse2 not supported");
+        expected.update(&v);
+        actual.update(&v);
+        assert_eq!(expected.finalize(), actual.finalize())
+    }
+}
@@ -1,4 +1,8 @@
-const NMAX: usize = 5536;
+use super::pclmulqdq::{self, Folder, FOLD_SIZE};
+
+// The largest multiple of `2 * CHUNK_SIZE` for which `b` cannot overflow, which is
+// 255 * n * (n + 1) / 2 + (n + 1) * (BASE - 1) <= 2^32 - 1, or n <= 5552.
+const NMAX: usize = 5504;
 const CHUNK_SIZE: usize = 32;
 
 #[derive(Copy, Clone, Debug)]
@@ -7,21 +11,8 @@ pub struct State {
 }
 
 impl State {
-    #[cfg(not(feature = "std"))]
-    pub fn new(initial: u32) -> Option<Self> {
-        if cfg!(target_feature = "ssse3") {
-            // SAFETY: Ensure that all required instructions are supported by the CPU.
-            Some(Self {
-                state: (initial & 0xffff, initial >> 16),
-            })
-        } else {
-            None
-        }
-    }
-
-    #[cfg(feature = "std")]
     pub fn new(initial: u32) -> Option<Self> {
-        if is_x86_feature_detected!("ssse3") {
+        if is_supported() {
             // SAFETY: Ensure that all required instructions are supported by the CPU.
             Some(Self {
                 state: (initial & 0xffff, initial >> 16),
@@ -44,21 +35,98 @@ impl State {
     }
 }
 
+#[cfg(target_feature = "ssse3")]
+pub(crate) fn is_supported() -> bool {
+    true
+}
+
+#[cfg(all(not(target_feature = "ssse3"), not(feature = "std")))]
+pub(crate) fn is_supported() -> bool {
+    super::cpuid::has_ssse3()
+}
+
+#[cfg(all(not(target_feature = "ssse3"), feature = "std"))]
+pub(crate) fn is_supported() -> bool {
+    is_x86_feature_detected!("ssse3")
+}
+
 #[target_feature(enable = "ssse3")]
-unsafe fn update_simd(mut a: u32, mut b: u32, buf: &[u8]) -> (u32, u32) {
+pub(crate) unsafe fn update_simd(mut a: u32, mut b: u32, buf: &[u8]) -> (u32, u32) {
     let chunks = buf.chunks_exact(NMAX);
     let mut remainder = chunks.remainder();
     for chunk in chunks {
-        add_reduce(&mut a, &mut b, chunk);
-        a %= crate::baseline::BASE;
-        b %= crate::baseline::BASE;
+        add_reduce(&mut a, &mut b, chunk, None, None);
+        a = crate::baseline::reduce(a);
+        b = crate::baseline::reduce(b);
     }
-    remainder = add_reduce(&mut a, &mut b, remainder);
-    crate::baseline::update_slow(a, b, remainder)
+    remainder = add_reduce(&mut a, &mut b, remainder, None, None);
+    update_tail(a, b, buf, remainder.len())
 }
 
+#[target_feature(enable = "ssse3")]
+pub(crate) unsafe fn update_copy_simd(
+    mut a: u32,
+    mut b: u32,
+    dst: &mut [u8],
+    src: &[u8],
+) -> (u32, u32) {
+    let chunks = src.chunks_exact(NMAX);
+    let mut remainder = chunks.remainder();
+    let mut dst_chunks = dst.chunks_exact_mut(NMAX);
+    for (chunk, dst_chunk) in chunks.zip(&mut dst_chunks) {
+        add_reduce(&mut a, &mut b, chunk, Some(dst_chunk), None);
+        a = crate::baseline::reduce(a);
+        b = crate::baseline::reduce(b);
+    }
+    let dst = dst_chunks.into_remainder();
+    remainder = add_reduce(&mut a, &mut b, remainder, Some(&mut *dst), None);
+    let copied = dst.len() - remainder.len();
+    dst[copied..].copy_from_slice(remainder);
+    update_tail(a, b, src, remainder.len())
+}
+
+#[target_feature(enable = "ssse3,pclmulqdq")]
+pub(crate) unsafe fn update_crc32_simd(
+    mut a: u32,
+    mut b: u32,
+    crc: u32,
+    buf: &[u8],
+) -> (u32, u32, u32) {
+    const PAIR_LEN: usize = 2 * CHUNK_SIZE;
+    if buf.len() < FOLD_SIZE + PAIR_LEN {
+        let (a, b) = update_simd(a, b, buf);
+        return (a, b, pclmulqdq::update_simd(crc, buf));
+    }
+    // The CRC-32 starts from its first bytes, so the pairs of the main loop follow them.
+    let (head, buf) = buf.split_at(FOLD_SIZE);
+    let mut folder = Folder::new(crc, head);
+    add_reduce(&mut a, &mut b, head, None, None);
+    a = crate::baseline::reduce(a);
+    b = crate::baseline::reduce(b);
+    let chunks = buf.chunks_exact(NMAX);
+    let mut remainder = chunks.remainder();
+    for chunk in chunks {
+        add_reduce(&mut a, &mut b, chunk, None, Some(&mut folder));
+        a = crate::baseline::reduce(a);
+        b = crate::baseline::reduce(b);
+    }
+    // Only whole pairs are folded, so the CRC-32 finishes with the block after them.
+    let unfolded = &remainder[remainder.len() / PAIR_LEN * PAIR_LEN..];
+    remainder = add_reduce(&mut a, &mut b, remainder, None, Some(&mut folder));
+    let (a, b) = update_tail(a, b, buf, remainder.len());
+    (a, b, folder.finish(unfolded))
+}
+
+// When `dst` is given, each block is also stored to the same offset in it, so that copying the
+// input costs no extra loads. When `crc` is given, each pair of blocks is also folded into it.
 #[inline(always)]
-unsafe fn add_reduce<'a>(a: &mut u32, b: &mut u32, chunk: &'a [u8]) -> &'a [u8] {
+unsafe fn add_reduce<'a>(
+    a: &mut u32,
+    b: &mut u32,
+    chunk: &'a [u8],
+    mut dst: Option<&mut [u8]>,
+    mut crc: Option<&mut Folder>,
+) -> &'a [u8] {
     #[cfg(target_arch = "x86")]
     use core::arch::x86::*;
     #[cfg(target_arch = "x86_64")]
@@ -80,11 +148,58 @@ unsafe fn add_reduce<'a>(a: &mut u32, b: &mut u32, chunk: &'a [u8]) -> &'a [u8]
     let mut v_v2j = _mm_setzero_si128();
     let mut v_v2k = _mm_setzero_si128();
 
-    let inner_chunks = chunk.chunks_exact(CHUNK_SIZE);
+    // Pairs of blocks only extend the `v_v1`/`v_v2j` dependency chains once, and their weighted
+    // sums can share a `madd` because they cannot exceed 2 * 16065 in 16 bits.
+    let pairs = chunk.chunks_exact(2 * CHUNK_SIZE);
+    let inner_chunks = pairs.remainder().chunks_exact(CHUNK_SIZE);
     let remainder = inner_chunks.remainder();
-    for inner_chunk in inner_chunks {
-        let v_p_left = _mm_lddqu_si128((&inner_chunk).as_ptr() as *const __m128i);
-        let v_p_right = _mm_lddqu_si128((&&inner_chunk[16..]).as_ptr() as *const __m128i);
+    let pairs_len = pairs.len() * 2 * CHUNK_SIZE;
+    for (i, pair) in pairs.enumerate() {
+        let v_p_left0 = _mm_lddqu_si128(pair.as_ptr() as *const __m128i);
+        let v_p_right0 = _mm_lddqu_si128(pair[16..].as_ptr() as *const __m128i);
+        let v_p_left1 = _mm_lddqu_si128(pair[32..].as_ptr() as *const __m128i);
+        let v_p_right1 = _mm_lddqu_si128(pair[48..].as_ptr() as *const __m128i);
+        if let Some(dst) = dst.as_deref_mut() {
+            let dst = dst[i * 2 * CHUNK_SIZE..][..2 * CHUNK_SIZE].as_mut_ptr();
+            _mm_storeu_si128(dst as *mut __m128i, v_p_left0);
+            _mm_storeu_si128(dst.add(16) as *mut __m128i, v_p_right0);
+            _mm_storeu_si128(dst.add(32) as *mut __m128i, v_p_left1);
+            _mm_storeu_si128(dst.add(48) as *mut __m128i, v_p_right1);
+        }
+        if let Some(crc) = crc.as_deref_mut() {
+            crc.fold_vectors([v_p_left0, v_p_right0, v_p_left1, v_p_right1]);
+        }
+        let v_sum0 = _mm_add_epi32(
+            _mm_sad_epu8(v_p_left0, v_zeroes),
+            _mm_sad_epu8(v_p_right0, v_zeroes),
+        );
+        let v_sum1 = _mm_add_epi32(
+            _mm_sad_epu8(v_p_left1, v_zeroes),
+            _mm_sad_epu8(v_p_right1, v_zeroes),
+        );
+        // The second block is preceded by `v_v1` and the sum of the first one.
+        v_v2j = _mm_add_epi32(v_v2j, _mm_add_epi32(_mm_slli_epi32(v_v1, 1), v_sum0));
+        v_v1 = _mm_add_epi32(v_v1, _mm_add_epi32(v_sum0, v_sum1));
+        let v_mad_left = _mm_add_epi16(
+            _mm_maddubs_epi16(v_p_left0, v_weights_left),
+            _mm_maddubs_epi16(v_p_left1, v_weights_left),
+        );
+        let v_mad_right = _mm_add_epi16(
+            _mm_maddubs_epi16(v_p_right0, v_weights_right),
+            _mm_maddubs_epi16(v_p_right1, v_weights_right),
+        );
+        v_v2k = _mm_add_epi32(v_v2k, _mm_madd_epi16(v_ones, v_mad_left));
+        v_v2k = _mm_add_epi32(v_v2k, _mm_madd_epi16(v_ones, v_mad_right));
+    }
+
+    for (i, inner_chunk) in inner_chunks.enumerate() {
+        let v_p_left = _mm_lddqu_si128(inner_chunk.as_ptr() as *const __m128i);
+        let v_p_right = _mm_lddqu_si128(inner_chunk[16..].as_ptr() as *const __m128i);
+        if let Some(dst) = dst.as_deref_mut() {
+            let dst = dst[pairs_len + i * CHUNK_SIZE..][..CHUNK_SIZE].as_mut_ptr();
+            _mm_storeu_si128(dst as *mut __m128i, v_p_left);
+            _mm_storeu_si128(dst.add(16) as *mut __m128i, v_p_right);
+        }
         v_v2j = _mm_add_epi32(v_v2j, v_v1);
         v_v1 = _mm_add_epi32(v_v1, _mm_sad_epu8(v_p_left, v_zeroes));
         v_v1 = _mm_add_epi32(v_v1, _mm_sad_epu8(v_p_right, v_zeroes));
@@ -109,6 +224,55 @@ unsafe fn add_reduce<'a>(a: &mut u32, b: &mut u32, chunk: &'a [u8]) -> &'a [u8]
     remainder
 }
 
+// Process the last `len` bytes of `buf`, fewer than `CHUNK_SIZE`, and reduce the sums. The block
+// is loaded so that it ends at the end of `buf`, with the bytes before the tail masked out, so the
+// weights of the tail still count down to 1. Inputs shorter than a block use the scalar tail.
+#[inline(always)]
+unsafe fn update_tail(mut a: u32, mut b: u32, buf: &[u8], len: usize) -> (u32, u32) {
+    #[cfg(target_arch = "x86")]
+    use core::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use core::arch::x86_64::*;
+
+    if buf.len() < CHUNK_SIZE || len == 0 {
+        return crate::baseline::update_tail(a, b, &buf[buf.len() - len..]);
+    }
+    let v_zeroes = _mm_setzero_si128();
+    let v_ones = _mm_set1_epi16(1);
+    let v_weights_left = _mm_set_epi8(
+        17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 28, 29, 30, 31, 32,
+    );
+    let v_weights_right = _mm_set_epi8(1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16);
+
+    let block = &buf[buf.len() - CHUNK_SIZE..];
+    let v_len = _mm_set1_epi8(len as i8 + 1);
+    let v_p_left = _mm_and_si128(
+        _mm_lddqu_si128(block.as_ptr() as *const __m128i),
+        _mm_cmpgt_epi8(v_len, v_weights_left),
+    );
+    let v_p_right = _mm_and_si128(
+        _mm_lddqu_si128(block[16..].as_ptr() as *const __m128i),
+        _mm_cmpgt_epi8(v_len, v_weights_right),
+    );
+    let mut v_v1 = _mm_add_epi32(
+        _mm_sad_epu8(v_p_left, v_zeroes),
+        _mm_sad_epu8(v_p_right, v_zeroes),
+    );
+    let mut v_v2 = _mm_add_epi32(
+        _mm_madd_epi16(v_ones, _mm_maddubs_epi16(v_p_left, v_weights_left)),
+        _mm_madd_epi16(v_ones, _mm_maddubs_epi16(v_p_right, v_weights_right)),
+    );
+    v_v1 = _mm_add_epi32(v_v1, _mm_shuffle_epi32(v_v1, 78));
+    b += a * len as u32;
+    a += _mm_cvtsi128_si32(v_v1) as u32;
+
+    v_v2 = _mm_add_epi32(v_v2, _mm_shuffle_epi32(v_v2, 177));
+    v_v2 = _mm_add_epi32(v_v2, _mm_shuffle_epi32(v_v2, 78));
+    b += _mm_cvtsi128_si32(v_v2) as u32;
+
+    (crate::baseline::reduce(a), crate::baseline::reduce(b))
+}
+
 #[cfg(test)]
 mod tests {
     quickcheck::quickcheck! {
@@ -136,6 +300,28 @@ mod tests {
             }
             expected.hash() == actual.finalize()
         }
+
+        fn ssse3_copy_is_same_as_baseline(init: u32, buf: Vec<u8>) -> bool {
+            let mut expected = crate::baseline::State::new(init);
+            expected.update(&buf);
+            assert!(super::is_supported(), "ssse3 not supported");
+            let mut dst = vec![0; buf.len()];
+            let (a, b) =
+                unsafe { super::update_copy_simd(init & 0xffff, init >> 16, &mut dst, &buf) };
+            expected.finalize() == a | (b << 16) && dst == buf
+        }
+
+        fn ssse3_crc32_is_same_as_reference(init: u32, crc: u32, buf: Vec<u8>) -> bool {
+            let mut expected = crate::baseline::State::new(init);
+            expected.update(&buf);
+            let mut expected_crc = crc32fast::Hasher::new_with_initial(crc);
+            expected_crc.update(&buf);
+            assert!(super::is_supported(), "ssse3 not supported");
+            assert!(super::pclmulqdq::is_supported(), "pclmulqdq not supported");
+            let (a, b, actual_crc) =
+                unsafe { super::update_crc32_simd(init & 0xffff, init >> 16, crc, &buf) };
+            expected.finalize() == a | (b << 16) && expected_crc.finalize() == actual_crc
+        }
     }
 
     #[test]
@@ -147,4 +333,29 @@ mod tests {
         actual.update(&v);
         assert_eq!(expected.finalize(), actual.finalize())
     }
+
+    #[test]
+    fn ssse3_is_valid_for_every_tail_length() {
+        // The tails follow whole blocks, so they are loaded together with bytes to mask out.
+        let v: Vec<u8> = (0..4 * super::CHUNK_SIZE)
+            .map(|i| (i * 37) as u8 | 0x80)
+            .collect();
+        for len in 0..=v.len() {
+            let mut expected = crate::baseline::State::new(0xfff0_fff0);
+            let mut actual = super::State::new(0xfff0_fff0).expect("ssse3 not supported");
+            expected.update(&v[..len]);
+            actual.update(&v[..len]);
+            assert_eq!(expected.finalize(), actual.finalize(), "length {}", len);
+        }
+    }
+
+    #[test]
+    fn ssse3_is_valid_for_worst_case_input() {
+        let v = vec![0xff; super::NMAX * 4 + 127];
+        let mut expected = crate::baseline::State::new(0xfff0_fff0);
+        let mut actual = super::State::new(0xfff0_fff0).expect("ssse3 not supported");
+        expected.update(&v);
+        actual.update(&v);
+        assert_eq!(expected.finalize(), actual.finalize())
+    }
 }
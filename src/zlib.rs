@@ -0,0 +1,349 @@
+//! Support for the [RFC 1950](https://www.rfc-editor.org/rfc/rfc1950) zlib container format.
+//!
+//! A zlib stream consists of a 2-byte header, an optional 4-byte preset dictionary identifier,
+//! the compressed data, and a 4-byte big-endian Adler-32 trailer of the uncompressed data.
+//!
+//! ```
+//! use adler32fast::zlib::{self, Header};
+//!
+//! let (header, len) = Header::parse(&[0x78, 0x9c]).unwrap();
+//! assert_eq!(len, 2);
+//! assert_eq!(header.window_size(), 32 * 1024);
+//! assert_eq!(&*header.to_bytes(), &[0x78, 0x9c]);
+//! assert_eq!(zlib::trailer(0x062c_0215), [0x06, 0x2c, 0x02, 0x15]);
+//! ```
+//!
+//! With the `std` feature enabled, [`StoredEncoder`] and [`StoredDecoder`] read and write
+//! complete zlib streams made of uncompressed deflate blocks.
+
+#[cfg(not(feature = "std"))]
+use core::{fmt, ops::Deref};
+#[cfg(feature = "std")]
+use std::{fmt, ops::Deref};
+
+use crate::{Adler32, ChecksumMismatch};
+
+#[cfg(feature = "std")]
+mod stored;
+
+#[cfg(feature = "std")]
+pub use stored::{StoredDecoder, StoredEncoder};
+
+/// The length of the Adler-32 trailer.
+pub const TRAILER_LEN: usize = 4;
+
+const MAX_HEADER_LEN: usize = 6;
+const CM_DEFLATE: u8 = 8;
+const FDICT: u8 = 1 << 5;
+const MIN_WINDOW_BITS: u8 = 8;
+const MAX_WINDOW_BITS: u8 = 15;
+
+/// The compression level hint stored in the `FLEVEL` field of the header.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CompressionLevel {
+    /// `FLEVEL = 0`: the compressor used the fastest algorithm.
+    Fastest = 0,
+    /// `FLEVEL = 1`: the compressor used a fast algorithm.
+    Fast = 1,
+    /// `FLEVEL = 2`: the compressor used the default algorithm.
+    Default = 2,
+    /// `FLEVEL = 3`: the compressor used maximum compression, the slowest algorithm.
+    Maximum = 3,
+}
+
+impl CompressionLevel {
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0b11 {
+            0 => Self::Fastest,
+            1 => Self::Fast,
+            2 => Self::Default,
+            _ => Self::Maximum,
+        }
+    }
+}
+
+/// The errors that can occur while parsing or constructing a zlib header.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HeaderError {
+    /// The input ended before the end of the header.
+    Truncated,
+    /// The compression method is not deflate (`CM = 8`).
+    UnsupportedMethod(u8),
+    /// The base-2 logarithm of the window size is outside of `8..=15`.
+    InvalidWindowBits(u8),
+    /// The `FCHECK` bits do not validate the header.
+    InvalidCheck,
+}
+
+impl fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Truncated => write!(f, "zlib header is truncated"),
+            Self::UnsupportedMethod(cm) => {
+                write!(f, "unsupported zlib compression method {}", cm)
+            }
+            Self::InvalidWindowBits(bits) => write!(f, "invalid zlib window bits {}", bits),
+            Self::InvalidCheck => write!(f, "zlib header check bits are invalid"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for HeaderError {}
+
+/// Represents a zlib stream header.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Header {
+    window_bits: u8,
+    level: CompressionLevel,
+    dict_id: Option<u32>,
+}
+
+impl Header {
+    /// Create a new `Header`.
+    ///
+    /// `window_bits` is the base-2 logarithm of the LZ77 window size and must be within
+    /// `8..=15`. `dict_id` is the Adler-32 of the preset dictionary, if any.
+    pub fn new(
+        window_bits: u8,
+        level: CompressionLevel,
+        dict_id: Option<u32>,
+    ) -> Result<Self, HeaderError> {
+        if !(MIN_WINDOW_BITS..=MAX_WINDOW_BITS).contains(&window_bits) {
+            return Err(HeaderError::InvalidWindowBits(window_bits));
+        }
+        Ok(Self {
+            window_bits,
+            level,
+            dict_id,
+        })
+    }
+
+    /// Parse a header from the start of `bytes`.
+    ///
+    /// On success, this returns the header and the number of bytes it occupied.
+    pub fn parse(bytes: &[u8]) -> Result<(Self, usize), HeaderError> {
+        if bytes.len() < 2 {
+            return Err(HeaderError::Truncated);
+        }
+        let (cmf, flg) = (bytes[0], bytes[1]);
+        if cmf & 0x0f != CM_DEFLATE {
+            return Err(HeaderError::UnsupportedMethod(cmf & 0x0f));
+        }
+        let window_bits = (cmf >> 4) + MIN_WINDOW_BITS;
+        if window_bits > MAX_WINDOW_BITS {
+            return Err(HeaderError::InvalidWindowBits(window_bits));
+        }
+        if (u16::from(cmf) << 8 | u16::from(flg)) % 31 != 0 {
+            return Err(HeaderError::InvalidCheck);
+        }
+        let level = CompressionLevel::from_bits(flg >> 6);
+        if flg & FDICT == 0 {
+            return Ok((Self::new(window_bits, level, None)?, 2));
+        }
+        if bytes.len() < MAX_HEADER_LEN {
+            return Err(HeaderError::Truncated);
+        }
+        let dict_id = u32::from_be_bytes([bytes[2], bytes[3], bytes[4], bytes[5]]);
+        Ok((
+            Self::new(window_bits, level, Some(dict_id))?,
+            MAX_HEADER_LEN,
+        ))
+    }
+
+    /// Return the base-2 logarithm of the window size.
+    pub fn window_bits(&self) -> u8 {
+        self.window_bits
+    }
+
+    /// Return the window size in bytes.
+    pub fn window_size(&self) -> usize {
+        1 << self.window_bits
+    }
+
+    /// Return the compression level hint.
+    pub fn level(&self) -> CompressionLevel {
+        self.level
+    }
+
+    /// Return the preset dictionary identifier, if any.
+    pub fn dict_id(&self) -> Option<u32> {
+        self.dict_id
+    }
+
+    /// Return the encoded header, including the check bits.
+    pub fn to_bytes(&self) -> HeaderBytes {
+        let cmf = (self.window_bits - MIN_WINDOW_BITS) << 4 | CM_DEFLATE;
+        let mut flg = (self.level as u8) << 6;
+        if self.dict_id.is_some() {
+            flg |= FDICT;
+        }
+        let rem = (u16::from(cmf) << 8 | u16::from(flg)) % 31;
+        if rem != 0 {
+            flg += 31 - rem as u8;
+        }
+
+        let mut bytes = [cmf, flg, 0, 0, 0, 0];
+        let len = match self.dict_id {
+            Some(dict_id) => {
+                bytes[2..].copy_from_slice(&dict_id.to_be_bytes());
+                MAX_HEADER_LEN
+            }
+            None => 2,
+        };
+        HeaderBytes { bytes, len }
+    }
+}
+
+impl Default for Header {
+    /// Return the header used by zlib's default settings (`78 9c`).
+    fn default() -> Self {
+        Self {
+            window_bits: MAX_WINDOW_BITS,
+            level: CompressionLevel::Default,
+            dict_id: None,
+        }
+    }
+}
+
+/// An encoded zlib header.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HeaderBytes {
+    bytes: [u8; MAX_HEADER_LEN],
+    len: usize,
+}
+
+impl Deref for HeaderBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+impl AsRef<[u8]> for HeaderBytes {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+/// Compute the `DICTID` of a preset dictionary.
+pub fn dictionary_id(dictionary: &[u8]) -> u32 {
+    let mut adler32 = Adler32::new();
+    adler32.update(dictionary);
+    adler32.as_u32()
+}
+
+/// Encode the trailer for the given Adler-32 checksum.
+pub fn trailer(checksum: u32) -> [u8; TRAILER_LEN] {
+    checksum.to_be_bytes()
+}
+
+/// Verify an encoded trailer against the checksum of the uncompressed data.
+pub fn verify_trailer(
+    adler32: &Adler32,
+    trailer: [u8; TRAILER_LEN],
+) -> Result<(), ChecksumMismatch> {
+    let expected = u32::from_be_bytes(trailer);
+    let actual = adler32.as_u32();
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(ChecksumMismatch { expected, actual })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CompressionLevel, Header, HeaderError};
+    use crate::Adler32;
+
+    const LEVELS: [CompressionLevel; 4] = [
+        CompressionLevel::Fastest,
+        CompressionLevel::Fast,
+        CompressionLevel::Default,
+        CompressionLevel::Maximum,
+    ];
+
+    #[test]
+    fn parses_zlib_headers() {
+        for (bytes, level) in [[0x78, 0x01], [0x78, 0x5e], [0x78, 0x9c], [0x78, 0xda]]
+            .iter()
+            .zip(LEVELS.iter())
+        {
+            let (header, len) = Header::parse(bytes).unwrap();
+            assert_eq!(len, 2);
+            assert_eq!(header.window_bits(), 15);
+            assert_eq!(header.level(), *level);
+            assert_eq!(header.dict_id(), None);
+            assert_eq!(&*header.to_bytes(), bytes);
+        }
+    }
+
+    #[test]
+    fn header_round_trips() {
+        for window_bits in 8..=15 {
+            for &level in &LEVELS {
+                for &dict_id in &[None, Some(0), Some(0x1234_5678)] {
+                    let header = Header::new(window_bits, level, dict_id).unwrap();
+                    let bytes = header.to_bytes();
+                    assert_eq!(Header::parse(&bytes), Ok((header, bytes.len())));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_headers() {
+        assert_eq!(Header::parse(&[0x78]), Err(HeaderError::Truncated));
+        assert_eq!(
+            Header::parse(&[0x79, 0x9c]),
+            Err(HeaderError::UnsupportedMethod(9))
+        );
+        assert_eq!(
+            Header::parse(&[0x88, 0x98]),
+            Err(HeaderError::InvalidWindowBits(16))
+        );
+        assert_eq!(Header::parse(&[0x78, 0x9d]), Err(HeaderError::InvalidCheck));
+        assert_eq!(
+            Header::parse(&[0x78, 0xbb, 0, 0]),
+            Err(HeaderError::Truncated)
+        );
+        assert_eq!(
+            Header::new(16, CompressionLevel::Default, None),
+            Err(HeaderError::InvalidWindowBits(16))
+        );
+    }
+
+    #[test]
+    fn dictionary_id_is_adler32() {
+        let dictionary = b"the quick brown fox";
+        let header = Header::new(
+            15,
+            CompressionLevel::Default,
+            Some(super::dictionary_id(dictionary)),
+        )
+        .unwrap();
+        let mut adler32 = Adler32::new();
+        adler32.update(dictionary);
+        assert_eq!(&header.to_bytes()[2..], &adler32.as_u32().to_be_bytes());
+    }
+
+    #[test]
+    fn verifies_trailer() {
+        // zlib.compress(b"hello")
+        let stream = [
+            0x78, 0x9c, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00, 0x06, 0x2c, 0x02, 0x15,
+        ];
+        let mut adler32 = Adler32::new();
+        adler32.update(b"hello");
+        let trailer = [stream[9], stream[10], stream[11], stream[12]];
+        assert_eq!(super::trailer(adler32.as_u32()), trailer);
+        assert!(super::verify_trailer(&adler32, trailer).is_ok());
+
+        adler32.update(b"!");
+        let mismatch = super::verify_trailer(&adler32, trailer).unwrap_err();
+        assert_eq!(mismatch.expected, 0x062c_0215);
+        assert_eq!(mismatch.actual, adler32.as_u32());
+    }
+}
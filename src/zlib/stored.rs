@@ -0,0 +1,318 @@
+use std::io::{self, Read, Write};
+
+use super::{CompressionLevel, Header, TRAILER_LEN};
+use crate::Adler32;
+
+// The largest payload that fits in a single stored block.
+const MAX_STORED_LEN: usize = 0xffff;
+const BLOCK_HEADER_LEN: usize = 5;
+const BFINAL: u8 = 1;
+const BTYPE_MASK: u8 = 0b110;
+
+fn invalid_data<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+/// A writer which produces a zlib stream made of uncompressed (stored) deflate blocks.
+///
+/// Data is buffered into blocks of up to 65535 bytes. [`finish`](StoredEncoder::finish) must be
+/// called to write the final block and the Adler-32 trailer.
+#[derive(Debug)]
+pub struct StoredEncoder<W: Write> {
+    inner: W,
+    header: Option<Header>,
+    buf: Vec<u8>,
+    adler32: Adler32,
+}
+
+impl<W: Write> StoredEncoder<W> {
+    /// Create a new `StoredEncoder` which writes the header zlib uses for level 0 (`78 01`).
+    pub fn new(inner: W) -> Self {
+        let header =
+            Header::new(15, CompressionLevel::Fastest, None).expect("default window size is valid");
+        Self::with_header(inner, header)
+    }
+
+    /// Create a new `StoredEncoder` which writes the given header.
+    pub fn with_header(inner: W, header: Header) -> Self {
+        Self {
+            inner,
+            header: Some(header),
+            buf: Vec::with_capacity(MAX_STORED_LEN),
+            adler32: Adler32::new(),
+        }
+    }
+
+    /// Return the Adler-32 value of the data written so far.
+    pub fn checksum(&self) -> u32 {
+        self.adler32.as_u32()
+    }
+
+    /// Return a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Write the final block and the trailer, returning the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.write_block(true)?;
+        self.inner
+            .write_all(&super::trailer(self.adler32.as_u32()))?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        if let Some(header) = self.header.take() {
+            self.inner.write_all(&header.to_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn write_block(&mut self, last: bool) -> io::Result<()> {
+        self.write_header()?;
+        let len = self.buf.len() as u16;
+        let mut block_header = [if last { BFINAL } else { 0 }, 0, 0, 0, 0];
+        block_header[1..3].copy_from_slice(&len.to_le_bytes());
+        block_header[3..].copy_from_slice(&(!len).to_le_bytes());
+        self.inner.write_all(&block_header)?;
+        self.inner.write_all(&self.buf)?;
+        self.buf.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for StoredEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.buf.len() == MAX_STORED_LEN {
+            self.write_block(false)?;
+        }
+        let n = buf.len().min(MAX_STORED_LEN - self.buf.len());
+        self.buf.extend_from_slice(&buf[..n]);
+        self.adler32.update(&buf[..n]);
+        Ok(n)
+    }
+
+    /// Write any buffered data as a non-final block and flush the underlying writer.
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buf.is_empty() {
+            self.write_block(false)?;
+        }
+        self.inner.flush()
+    }
+}
+
+/// A reader which decodes a zlib stream made of uncompressed (stored) deflate blocks.
+///
+/// Streams containing compressed blocks are rejected with `io::ErrorKind::InvalidData`. Once
+/// the final block has been read, the Adler-32 trailer is verified and a mismatch is reported
+/// as an `io::ErrorKind::InvalidData` error wrapping a
+/// [`ChecksumMismatch`](crate::ChecksumMismatch).
+#[derive(Debug)]
+pub struct StoredDecoder<R> {
+    inner: R,
+    header: Option<Header>,
+    remaining: usize,
+    last_block: bool,
+    done: bool,
+    adler32: Adler32,
+}
+
+impl<R: Read> StoredDecoder<R> {
+    /// Create a new `StoredDecoder` wrapping the given reader.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            header: None,
+            remaining: 0,
+            last_block: false,
+            done: false,
+            adler32: Adler32::new(),
+        }
+    }
+
+    /// Return the stream header, if it has been read.
+    pub fn header(&self) -> Option<&Header> {
+        self.header.as_ref()
+    }
+
+    /// Return the Adler-32 value of the data decoded so far.
+    pub fn checksum(&self) -> u32 {
+        self.adler32.as_u32()
+    }
+
+    /// Consume the decoder, returning the underlying reader.
+    ///
+    /// Once the stream has been fully read, the underlying reader is positioned just after the
+    /// trailer.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn read_header(&mut self) -> io::Result<()> {
+        let mut bytes = [0; 6];
+        self.inner.read_exact(&mut bytes[..2])?;
+        if bytes[1] & super::FDICT != 0 {
+            self.inner.read_exact(&mut bytes[2..])?;
+        }
+        let (header, _) = Header::parse(&bytes).map_err(invalid_data)?;
+        self.header = Some(header);
+        Ok(())
+    }
+
+    fn read_block_header(&mut self) -> io::Result<()> {
+        let mut bytes = [0; BLOCK_HEADER_LEN];
+        self.inner.read_exact(&mut bytes)?;
+        if bytes[0] & BTYPE_MASK != 0 {
+            return Err(invalid_data("only stored deflate blocks are supported"));
+        }
+        let len = u16::from_le_bytes([bytes[1], bytes[2]]);
+        let nlen = u16::from_le_bytes([bytes[3], bytes[4]]);
+        if len != !nlen {
+            return Err(invalid_data(
+                "stored block length does not match its complement",
+            ));
+        }
+        self.last_block = bytes[0] & BFINAL != 0;
+        self.remaining = usize::from(len);
+        Ok(())
+    }
+
+    fn read_trailer(&mut self) -> io::Result<()> {
+        let mut trailer = [0; TRAILER_LEN];
+        self.inner.read_exact(&mut trailer)?;
+        super::verify_trailer(&self.adler32, trailer).map_err(invalid_data)?;
+        self.done = true;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for StoredDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.done {
+            return Ok(0);
+        }
+        if self.header.is_none() {
+            self.read_header()?;
+        }
+        while self.remaining == 0 {
+            if self.last_block {
+                self.read_trailer()?;
+                return Ok(0);
+            }
+            self.read_block_header()?;
+        }
+
+        let len = buf.len().min(self.remaining);
+        let n = self.inner.read(&mut buf[..len])?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.adler32.update(&buf[..n]);
+        self.remaining -= n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read, Write};
+
+    use super::{StoredDecoder, StoredEncoder};
+    use crate::ChecksumMismatch;
+
+    // zlib.compress(b"hello", 0)
+    const HELLO: [u8; 16] = [
+        0x78, 0x01, 0x01, 0x05, 0x00, 0xfa, 0xff, 0x68, 0x65, 0x6c, 0x6c, 0x6f, 0x06, 0x2c, 0x02,
+        0x15,
+    ];
+
+    fn encode(buf: &[u8]) -> Vec<u8> {
+        let mut encoder = StoredEncoder::new(Vec::new());
+        encoder.write_all(buf).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn decode(stream: &[u8]) -> io::Result<Vec<u8>> {
+        let mut out = Vec::new();
+        StoredDecoder::new(stream).read_to_end(&mut out)?;
+        Ok(out)
+    }
+
+    quickcheck::quickcheck! {
+        fn stored_round_trips(buf: Vec<u8>) -> bool {
+            decode(&encode(&buf)).unwrap() == buf
+        }
+    }
+
+    #[test]
+    fn stored_matches_zlib() {
+        assert_eq!(encode(b"hello"), HELLO);
+        assert_eq!(decode(&HELLO).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn stored_splits_large_input_into_blocks() {
+        for &len in &[0usize, 0xffff, 0x10000, 200_000] {
+            let buf: Vec<u8> = (0..len).map(|i| (i % 253) as u8).collect();
+            let stream = encode(&buf);
+            let blocks = std::cmp::max(1, len.div_ceil(0xffff));
+            assert_eq!(stream.len(), 2 + blocks * 5 + len + 4);
+            assert_eq!(decode(&stream).unwrap(), buf);
+        }
+    }
+
+    #[test]
+    fn stored_flush_emits_partial_block() {
+        let mut encoder = StoredEncoder::new(Vec::new());
+        encoder.write_all(b"hel").unwrap();
+        encoder.flush().unwrap();
+        encoder.write_all(b"lo").unwrap();
+        let stream = encoder.finish().unwrap();
+        assert_eq!(stream.len(), 2 + 5 + 3 + 5 + 2 + 4);
+        assert_eq!(decode(&stream).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn stored_rejects_invalid_trailer() {
+        let mut stream = HELLO;
+        stream[15] ^= 1;
+        let err = decode(&stream).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.get_ref().unwrap().is::<ChecksumMismatch>());
+    }
+
+    #[test]
+    fn stored_rejects_invalid_streams() {
+        // zlib.compress(b"hello")
+        let fixed = [
+            0x78, 0x9c, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00, 0x06, 0x2c, 0x02, 0x15,
+        ];
+        assert_eq!(
+            decode(&fixed).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        let mut bad_nlen = HELLO;
+        bad_nlen[5] ^= 1;
+        assert_eq!(
+            decode(&bad_nlen).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        let mut bad_header = HELLO;
+        bad_header[1] ^= 1;
+        assert_eq!(
+            decode(&bad_header).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        assert_eq!(
+            decode(&HELLO[..10]).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+}